@@ -0,0 +1,184 @@
+//! # Compiler of JS modules into standalone executables
+//!
+//! This tool works like `qjsc` from QuickJS: it compiles the entry module with all modules which it imports into bytecode
+//! and generates the source of a program which runs the bundled modules.
+//!
+//! The Rust stub uses `rquickjs` to load the bundle, so it can be built as a regular Cargo project (see `--project` option).
+//! The C stub uses the QuickJS C API directly and should be linked with the `libquickjs` library which is built by `rquickjs-sys`.
+
+use rquickjs::{bundle::Builder, Result};
+use std::{env, fs, path::Path, process};
+
+mod stub;
+use stub::Lang;
+
+const USAGE: &str = "\
+Usage: rquickjs-cli [OPTIONS] <ENTRY>
+
+Compile JS module <ENTRY> with all imported modules into a standalone executable stub
+
+Options:
+  -o, --output <FILE>     Write generated stub to file instead of stdout
+  -P, --project <DIR>     Write Cargo project which builds an executable (Rust only)
+  -I, --path <DIR>        Add search path for modules (default is current directory)
+  -p, --pattern <PAT>     Add search pattern for modules (default is '{}.js')
+  -l, --lang <LANG>       Language of generated stub: rust or c (default is rust)
+  -n, --name <NAME>       Name of executable for generated project (default is entry name)
+      --rquickjs-path <DIR>
+                          Use local sources of rquickjs in generated project instead of crates.io
+  -h, --help              Print this help
+  -V, --version           Print version
+";
+
+/// Command line options
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    entry: String,
+    output: Option<String>,
+    project: Option<String>,
+    paths: Vec<String>,
+    patterns: Vec<String>,
+    lang: Lang,
+    name: Option<String>,
+    rquickjs_path: Option<String>,
+}
+
+/// The action which is requested by command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Compile(Options),
+    Help,
+    Version,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> std::result::Result<Command, String> {
+    let mut options = Options::default();
+    let mut entry = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value of option '{}'", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-o" | "--output" => options.output = Some(value(&arg)?),
+            "-P" | "--project" => options.project = Some(value(&arg)?),
+            "-I" | "--path" => options.paths.push(value(&arg)?),
+            "-p" | "--pattern" => options.patterns.push(value(&arg)?),
+            "-l" | "--lang" => options.lang = value(&arg)?.parse()?,
+            "-n" | "--name" => options.name = Some(value(&arg)?),
+            "--rquickjs-path" => options.rquickjs_path = Some(value(&arg)?),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option '{}'", arg))
+            }
+            _ if entry.is_some() => return Err(format!("Unexpected argument '{}'", arg)),
+            _ => entry = Some(arg),
+        }
+    }
+
+    options.entry = entry.ok_or("Missing entry module")?;
+    if options.project.is_some() && options.lang != Lang::Rust {
+        return Err("The project can be generated for Rust stub only".into());
+    }
+    if options.rquickjs_path.is_some() && options.project.is_none() {
+        return Err("The path of rquickjs can be used with project only".into());
+    }
+    Ok(Command::Compile(options))
+}
+
+fn compile(options: &Options) -> Result<()> {
+    let mut builder = Builder::new();
+    if options.paths.is_empty() {
+        builder.add_path(".");
+    }
+    for path in &options.paths {
+        builder.add_path(path);
+    }
+    for pattern in &options.patterns {
+        builder.add_pattern(pattern);
+    }
+    builder.add_module(&options.entry);
+
+    let code = match options.lang {
+        Lang::Rust => stub::rust(&options.entry, &builder.to_rust()?),
+        Lang::C => stub::c(&options.entry, &builder.compile()?)?,
+    };
+
+    if let Some(dir) = &options.project {
+        let dir = Path::new(dir);
+        let name = options
+            .name
+            .clone()
+            .unwrap_or_else(|| stub::package_name(&options.entry));
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(
+            dir.join("Cargo.toml"),
+            stub::cargo_toml(&name, options.rquickjs_path.as_deref()),
+        )?;
+        fs::write(dir.join("src").join("main.rs"), code)?;
+    } else if let Some(output) = &options.output {
+        fs::write(output, code)?;
+    } else {
+        print!("{}", code);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Compile(options)) => {
+            if let Err(error) = compile(&options) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Help) => print!("{}", USAGE),
+        Ok(Command::Version) => println!("rquickjs-cli {}", env!("CARGO_PKG_VERSION")),
+        Err(error) => {
+            eprintln!("Error: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(
+            parse(&["-I", "src", "--lang", "c", "-o", "app.c", "app"]),
+            Ok(Command::Compile(Options {
+                entry: "app".into(),
+                output: Some("app.c".into()),
+                paths: vec!["src".into()],
+                lang: Lang::C,
+                ..Options::default()
+            }))
+        );
+        assert_eq!(parse(&["app", "-h"]), Ok(Command::Help));
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["app", "-o"]).is_err());
+        assert!(parse(&["app", "--lang", "go"]).is_err());
+        assert!(parse(&["app", "other"]).is_err());
+        assert!(parse(&["-l", "c", "-P", "out", "app"]).is_err());
+        assert_eq!(
+            parse(&["-P", "out", "--rquickjs-path", "../rquickjs", "app"]),
+            Ok(Command::Compile(Options {
+                entry: "app".into(),
+                project: Some("out".into()),
+                rquickjs_path: Some("../rquickjs".into()),
+                ..Options::default()
+            }))
+        );
+        assert!(parse(&["--rquickjs-path", "../rquickjs", "app"]).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use rquickjs::{bundle::strip_header, Result};
+use std::{fmt::Write, path::Path, str::FromStr};
+
+/// The language of generated stub
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    Rust,
+    C,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "rust" | "rs" => Ok(Lang::Rust),
+            "c" => Ok(Lang::C),
+            _ => Err(format!("Unsupported language '{}'", s)),
+        }
+    }
+}
+
+/// The source of main module which imports the entry module
+fn main_source(entry: &str) -> String {
+    format!("import {:?};", entry)
+}
+
+/// Generate Rust program which runs the bundle
+///
+/// The bundle is an expression generated by [`Builder::to_rust`](rquickjs::bundle::Builder::to_rust).
+pub fn rust(entry: &str, bundle: &str) -> String {
+    format!(
+        r#"// Generated by rquickjs-cli {version}
+use rquickjs::{{Bundle, Context, Func, Runtime, ScaBundleData}};
+
+static BUNDLE: Bundle<ScaBundleData<&[u8]>> = {bundle};
+
+fn print(msg: String) {{
+    println!("{{}}", msg);
+}}
+
+fn run() -> rquickjs::Result<()> {{
+    let rt = Runtime::new()?;
+    let ctx = Context::full(&rt)?;
+    rt.set_loader(BUNDLE, BUNDLE);
+
+    ctx.with(|ctx| -> rquickjs::Result<()> {{
+        ctx.globals().set("print", Func::new("print", print))?;
+        ctx.compile("<main>", {source:?})?;
+        Ok(())
+    }})?;
+
+    while rt.is_job_pending() {{
+        rt.execute_pending_job()?;
+    }}
+    Ok(())
+}}
+
+fn main() {{
+    if let Err(error) = run() {{
+        eprintln!("{{}}", error);
+        std::process::exit(1);
+    }}
+}}
+"#,
+        version = env!("CARGO_PKG_VERSION"),
+        bundle = bundle.trim_end(),
+        source = main_source(entry),
+    )
+}
+
+/// Generate manifest of Cargo project which builds the Rust stub
+///
+/// The project depends on the exact version of rquickjs which the CLI was built with
+/// because the bytecode of bundle can be loaded only by the same version of QuickJS.
+/// The local sources of rquickjs can be used instead by passing its path.
+pub fn cargo_toml(name: &str, lib_path: Option<&str>) -> String {
+    let mut toml = format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies.rquickjs]
+version = "={version}"
+"#,
+        name = name,
+        version = env!("CARGO_PKG_VERSION"),
+    );
+    if let Some(path) = lib_path {
+        writeln!(toml, "path = {:?}", path).unwrap();
+    }
+    toml.push_str(
+        r#"default-features = false
+features = ["loader"]
+"#,
+    );
+    toml
+}
+
+/// Make valid package name from the name of entry module
+pub fn package_name(entry: &str) -> String {
+    let name = entry
+        .rsplit('/')
+        .next()
+        .unwrap_or(entry)
+        .split('.')
+        .next()
+        .unwrap_or_default();
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() {
+        "main".into()
+    } else {
+        name
+    }
+}
+
+/// Generate C program which runs the bundle
+///
+/// The bytecodes is stored without versioned header since it is loaded using the QuickJS C API.
+pub fn c(entry: &str, modules: &[(String, Vec<u8>)]) -> Result<String> {
+    let mut code = String::new();
+    writeln!(
+        code,
+        r#"/* Generated by rquickjs-cli {version}
+ *
+ * Build it with the same version of QuickJS which is used by rquickjs-sys, for example:
+ *   cc -I<quickjs-dir> main.c <out-dir>/libquickjs.a -lm -lpthread -ldl
+ */
+#include <stdio.h>
+#include <string.h>
+#include "quickjs.h"
+"#,
+        version = env!("CARGO_PKG_VERSION"),
+    )
+    .unwrap();
+
+    for (index, (_, data)) in modules.iter().enumerate() {
+        let data = strip_header(data)?;
+        writeln!(
+            code,
+            "static const uint8_t module_{}[{}] = {{",
+            index,
+            data.len()
+        )
+        .unwrap();
+        for chunk in data.chunks(16) {
+            code.push_str("   ");
+            for byte in chunk {
+                write!(code, " 0x{:02x},", byte).unwrap();
+            }
+            code.push('\n');
+        }
+        code.push_str("};\n\n");
+    }
+
+    code.push_str(
+        "static const struct {\n    const char *name;\n    const uint8_t *data;\n    size_t size;\n} modules[] = {\n",
+    );
+    for (index, (name, _)) in modules.iter().enumerate() {
+        writeln!(
+            code,
+            "    {{ {}, module_{}, sizeof(module_{}) }},",
+            c_string(name),
+            index,
+            index
+        )
+        .unwrap();
+    }
+    code.push_str("};\n");
+
+    write!(
+        code,
+        r#"
+static JSModuleDef *load_module(JSContext *ctx, const char *name, void *opaque) {{
+    size_t i;
+    for (i = 0; i < sizeof(modules) / sizeof(modules[0]); i++) {{
+        if (strcmp(modules[i].name, name) == 0) {{
+            JSValue obj = JS_ReadObject(ctx, modules[i].data, modules[i].size, JS_READ_OBJ_BYTECODE);
+            JSModuleDef *module;
+            if (JS_IsException(obj)) {{
+                return NULL;
+            }}
+            module = JS_VALUE_GET_PTR(obj);
+            JS_FreeValue(ctx, obj);
+            return module;
+        }}
+    }}
+    JS_ThrowReferenceError(ctx, "could not load module '%s'", name);
+    return NULL;
+}}
+
+static void print_exception(JSContext *ctx) {{
+    JSValue exception = JS_GetException(ctx);
+    const char *message = JS_ToCString(ctx, exception);
+    if (message) {{
+        fprintf(stderr, "%s\n", message);
+        JS_FreeCString(ctx, message);
+    }}
+    if (JS_IsError(ctx, exception)) {{
+        JSValue stack = JS_GetPropertyStr(ctx, exception, "stack");
+        if (!JS_IsUndefined(stack)) {{
+            const char *trace = JS_ToCString(ctx, stack);
+            if (trace) {{
+                fprintf(stderr, "%s", trace);
+                JS_FreeCString(ctx, trace);
+            }}
+        }}
+        JS_FreeValue(ctx, stack);
+    }}
+    JS_FreeValue(ctx, exception);
+}}
+
+static JSValue print(JSContext *ctx, JSValueConst this_val, int argc, JSValueConst *argv) {{
+    int i;
+    for (i = 0; i < argc; i++) {{
+        const char *str = JS_ToCString(ctx, argv[i]);
+        if (!str) {{
+            return JS_EXCEPTION;
+        }}
+        printf(i ? " %s" : "%s", str);
+        JS_FreeCString(ctx, str);
+    }}
+    printf("\n");
+    return JS_UNDEFINED;
+}}
+
+int main(int argc, char **argv) {{
+    static const char source[] = {source};
+    JSRuntime *rt = JS_NewRuntime();
+    JSContext *ctx = JS_NewContext(rt);
+    JSValue global, result;
+    int status = 0;
+
+    JS_SetModuleLoaderFunc(rt, NULL, load_module, NULL);
+
+    global = JS_GetGlobalObject(ctx);
+    JS_SetPropertyStr(ctx, global, "print", JS_NewCFunction(ctx, print, "print", 1));
+    JS_FreeValue(ctx, global);
+
+    result = JS_Eval(ctx, source, sizeof(source) - 1, "<main>", JS_EVAL_TYPE_MODULE);
+    if (JS_IsException(result)) {{
+        print_exception(ctx);
+        status = 1;
+    }}
+    JS_FreeValue(ctx, result);
+
+    while (!status) {{
+        JSContext *job_ctx;
+        int res = JS_ExecutePendingJob(rt, &job_ctx);
+        if (res <= 0) {{
+            if (res < 0) {{
+                print_exception(job_ctx);
+                status = 1;
+            }}
+            break;
+        }}
+    }}
+
+    JS_FreeContext(ctx);
+    JS_FreeRuntime(rt);
+    return status;
+}}
+"#,
+        source = c_string(&main_source(entry)),
+    )
+    .unwrap();
+
+    Ok(code)
+}
+
+/// Write C string literal
+fn c_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' | b'\\' => write!(out, "\\{}", byte as char).unwrap(),
+            0x20..=0x7e => out.push(byte as char),
+            _ => write!(out, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs, process::Command};
+
+    #[test]
+    fn names() {
+        assert_eq!(package_name("src/my app.js"), "my_app");
+        assert_eq!(package_name("./Hello"), "hello");
+        assert_eq!(c_string("a\"b\\\n"), r#""a\"b\\\012""#);
+    }
+
+    #[test]
+    fn manifest() {
+        let toml = cargo_toml("stub", None);
+        assert!(toml.contains(&format!("version = \"={}\"\n", env!("CARGO_PKG_VERSION"))));
+        assert!(!toml.contains("path ="));
+        let toml = cargo_toml("stub", Some("../rquickjs"));
+        assert!(toml.contains("path = \"../rquickjs\"\n"));
+    }
+
+    // This test builds rquickjs with QuickJS so it is slow
+    #[test]
+    #[ignore]
+    fn rust_project() {
+        let dir =
+            std::env::temp_dir().join(format!("rquickjs_cli_stub_test_{}", std::process::id()));
+        let lib_path = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            cargo_toml("stub", Some(&lib_path.display().to_string())),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("src").join("main.rs"),
+            rust("main.js", "rquickjs::Bundle(&[])"),
+        )
+        .unwrap();
+
+        let status = Command::new(env!("CARGO"))
+            .arg("check")
+            .arg("--manifest-path")
+            .arg(dir.join("Cargo.toml"))
+            .arg("--offline")
+            .status()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(status.success());
+    }
+}
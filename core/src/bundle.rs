@@ -0,0 +1,367 @@
+//! Bundling of JS modules at build time
+//!
+//! The [`Builder`] compiles modules into bytecode and generates Rust code with the same data which is produced by the `embed` macro.
+//! It can be used in build scripts to bundle modules which is generated or located using the paths computed at build time.
+//!
+//! ```no_run
+//! // build.rs
+//! use rquickjs::bundle::Builder;
+//! use std::{env, path::Path};
+//!
+//! let out_file = Path::new(&env::var("OUT_DIR").unwrap()).join("bundle.rs");
+//! Builder::new()
+//!     .with_path("js")
+//!     .with_module("app")
+//!     .write_rust(out_file)
+//!     .unwrap();
+//! ```
+//!
+//! The generated code is an expression which can be included into the source:
+//!
+//! ```ignore
+//! use rquickjs::{Bundle, ScaBundleData};
+//!
+//! static BUNDLE: Bundle<ScaBundleData<&[u8]>> = include!(concat!(env!("OUT_DIR"), "/bundle.rs"));
+//! ```
+
+use crate::{Compile, Context, FileResolver, Module, Result, Runtime, ScriptLoader};
+use std::{fmt::Write, fs, path::Path};
+
+/// The builder of module bundles
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "bundle")))]
+#[derive(Debug, Clone)]
+pub struct Builder {
+    paths: Vec<String>,
+    patterns: Vec<String>,
+    modules: Vec<String>,
+    phf: bool,
+    lib_crate: String,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            patterns: Vec::new(),
+            modules: Vec::new(),
+            phf: false,
+            lib_crate: "rquickjs".into(),
+        }
+    }
+}
+
+impl Builder {
+    /// Create new bundle builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add search path for modules
+    pub fn add_path<P: Into<String>>(&mut self, path: P) -> &mut Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Add search path for modules
+    #[must_use]
+    pub fn with_path<P: Into<String>>(mut self, path: P) -> Self {
+        self.add_path(path);
+        self
+    }
+
+    /// Add search pattern for modules
+    ///
+    /// The extensions of patterns is also used to filter loaded modules.
+    /// See [`FileResolver::add_pattern`] for details.
+    pub fn add_pattern<P: Into<String>>(&mut self, pattern: P) -> &mut Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Add search pattern for modules
+    #[must_use]
+    pub fn with_pattern<P: Into<String>>(mut self, pattern: P) -> Self {
+        self.add_pattern(pattern);
+        self
+    }
+
+    /// Add module to bundle
+    ///
+    /// The module will be bundled with all modules which it imports.
+    pub fn add_module<N: Into<String>>(&mut self, name: N) -> &mut Self {
+        self.modules.push(name.into());
+        self
+    }
+
+    /// Add module to bundle
+    #[must_use]
+    pub fn with_module<N: Into<String>>(mut self, name: N) -> Self {
+        self.add_module(name);
+        self
+    }
+
+    /// Generate perfect hash map instead of static const array
+    ///
+    /// The generated data should be used as [`PhfBundleData`](crate::PhfBundleData) so the `phf` feature is required.
+    pub fn set_phf(&mut self, phf: bool) -> &mut Self {
+        self.phf = phf;
+        self
+    }
+
+    /// Generate perfect hash map instead of static const array
+    #[must_use]
+    pub fn with_phf(mut self, phf: bool) -> Self {
+        self.set_phf(phf);
+        self
+    }
+
+    /// Set the name of library crate which is used in generated code
+    ///
+    /// The default name is `rquickjs`.
+    pub fn set_lib_crate<N: Into<String>>(&mut self, name: N) -> &mut Self {
+        self.lib_crate = name.into();
+        self
+    }
+
+    /// Set the name of library crate which is used in generated code
+    #[must_use]
+    pub fn with_lib_crate<N: Into<String>>(mut self, name: N) -> Self {
+        self.set_lib_crate(name);
+        self
+    }
+
+    /// Compile modules into bytecode
+    ///
+    /// Returns module names with bytecodes.
+    pub fn compile(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let compile = Compile::new();
+
+        let mut resolver = compile.resolver(FileResolver::default());
+        for path in &self.paths {
+            resolver.add_path(path);
+        }
+        for pattern in &self.patterns {
+            resolver.add_pattern(pattern);
+        }
+
+        let mut loader = compile.loader(ScriptLoader::default());
+        for pattern in &self.patterns {
+            if let Some(extension) = Path::new(pattern)
+                .extension()
+                .and_then(|extension| extension.to_str())
+            {
+                loader.add_extension(extension);
+            }
+        }
+
+        let rt = Runtime::new()?;
+        let ctx = Context::full(&rt)?;
+
+        rt.set_loader(resolver, loader);
+
+        let source = self
+            .modules
+            .iter()
+            .map(|name| format!("import {:?};", name))
+            .collect::<Vec<_>>()
+            .join("");
+
+        ctx.with(|ctx| -> Result<()> {
+            let _ = Module::new(ctx, "<main>", source)?;
+            Ok(())
+        })?;
+
+        let bytecodes = compile
+            .bytecodes()
+            .into_iter()
+            .map(|(name, data)| (name.to_string(), data.to_vec()))
+            .collect();
+        Ok(bytecodes)
+    }
+
+    /// Compile modules and generate Rust code of bundle
+    ///
+    /// The generated code is an expression of type [`Bundle`](crate::Bundle).
+    pub fn to_rust(&self) -> Result<String> {
+        let entries = self.compile()?;
+        let lib_crate = &self.lib_crate;
+        let mut code = String::new();
+
+        if self.phf {
+            let names = entries
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            let state = phf_generator::generate_hash(&names);
+            let disps = state
+                .disps
+                .iter()
+                .map(|(d1, d2)| format!("({}, {})", d1, d2))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let entries = state
+                .map
+                .iter()
+                .map(|index| {
+                    let (name, data) = &entries[*index];
+                    write_entry(name, data)
+                })
+                .collect::<Vec<_>>()
+                .join(",\n");
+            write!(
+                code,
+                "{lib}::Bundle(&{lib}::phf::Map {{\n    key: {key},\n    disps: &[{disps}],\n    entries: &[\n{entries}\n    ],\n}})\n",
+                lib = lib_crate,
+                key = state.key,
+                disps = disps,
+                entries = entries,
+            )
+            .unwrap();
+        } else {
+            let entries = entries
+                .iter()
+                .map(|(name, data)| write_entry(name, data))
+                .collect::<Vec<_>>()
+                .join(",\n");
+            write!(
+                code,
+                "{lib}::Bundle(&[\n{entries}\n])\n",
+                lib = lib_crate,
+                entries = entries,
+            )
+            .unwrap();
+        }
+
+        Ok(code)
+    }
+
+    /// Compile modules and write generated Rust code of bundle to file
+    pub fn write_rust<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_rust()?)?;
+        Ok(())
+    }
+}
+
+/// Get raw QuickJS bytecode without versioned header
+///
+/// The bytecode produced by [`Builder::compile`] is prepended by the header which is validated by [`Module::read_object`].
+/// The raw bytecode is required to load modules using the QuickJS C API (`JS_ReadObject`) directly.
+pub fn strip_header(bytecode: &[u8]) -> Result<&[u8]> {
+    crate::value::bytecode::strip_header(bytecode)
+}
+
+/// Write bundle entry as a tuple of module name and byte string literal
+fn write_entry(name: &str, data: &[u8]) -> String {
+    let mut entry = format!("    ({:?}, b\"", name);
+    for byte in data {
+        match byte {
+            b'"' | b'\\' => write!(entry, "\\{}", *byte as char).unwrap(),
+            0x20..=0x7e => entry.push(*byte as char),
+            _ => write!(entry, "\\x{:02x}", byte).unwrap(),
+        }
+    }
+    entry.push_str("\")");
+    entry
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Bundle, Loader, Resolver, ScaBundleData};
+
+    #[cfg(feature = "phf")]
+    use crate::PhfBundleData;
+
+    #[test]
+    fn byte_string_entry() {
+        assert_eq!(
+            write_entry("lib/a", b"a\"\\\x00\xff"),
+            r#"    ("lib/a", b"a\"\\\x00\xff")"#
+        );
+    }
+
+    fn build(name: &str) -> Builder {
+        let dir = std::env::temp_dir().join(format!(
+            "rquickjs_bundle_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("app.js"),
+            "import { m } from 'lib'; export const n = m + 1;",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib.js"), "export const m = 2;").unwrap();
+        Builder::new()
+            .with_path(dir.display().to_string())
+            .with_module("app")
+    }
+
+    fn compile_static(builder: &Builder) -> ScaBundleData<&'static [u8]> {
+        let entries = builder
+            .compile()
+            .unwrap()
+            .into_iter()
+            .map(|(name, data)| {
+                (
+                    &*Box::leak(name.into_boxed_str()),
+                    &*Box::leak(data.into_boxed_slice()),
+                )
+            })
+            .collect::<Vec<_>>();
+        Box::leak(entries.into_boxed_slice())
+    }
+
+    fn eval_bundle<B>(bundle: B) -> i32
+    where
+        B: Resolver + Loader + Clone + 'static,
+    {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(bundle.clone(), bundle);
+        ctx.with(|ctx| {
+            ctx.compile("main", "import { n } from 'app'; globalThis.n = n;")
+                .unwrap();
+            ctx.globals().get("n").unwrap()
+        })
+    }
+
+    #[test]
+    fn sca_bundle() {
+        let builder = build("sca");
+        let code = builder.to_rust().unwrap();
+        assert!(code.starts_with("rquickjs::Bundle(&[\n"));
+        assert!(code.contains("(\"app\", b\""));
+        assert!(code.contains("(\"lib\", b\""));
+
+        let entries = compile_static(&builder);
+        assert_eq!(eval_bundle(Bundle(entries)), 3);
+    }
+
+    #[cfg(feature = "phf")]
+    #[test]
+    fn phf_bundle() {
+        let builder = build("phf").with_phf(true);
+        let code = builder.to_rust().unwrap();
+        assert!(code.starts_with("rquickjs::Bundle(&rquickjs::phf::Map {\n"));
+
+        // The same map as in generated code
+        let entries = compile_static(&builder);
+        let names = entries.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        let state = phf_generator::generate_hash(&names);
+        let map: PhfBundleData<&'static [u8]> = Box::leak(Box::new(phf::Map {
+            key: state.key,
+            disps: Box::leak(state.disps.into_boxed_slice()),
+            entries: Box::leak(
+                state
+                    .map
+                    .iter()
+                    .map(|index| entries[*index])
+                    .collect::<Vec<_>>()
+                    .into_boxed_slice(),
+            ),
+        }));
+        assert_eq!(eval_bundle(Bundle(map)), 3);
+    }
+}
@@ -0,0 +1,754 @@
+//! The `console` built-in
+//!
+//! The [`Console`] installs the global `console` object which formats the arguments like browsers and Node do
+//! and routes the messages to the [`Sink`].
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, console::{Console, Level}};
+//! # use std::sync::{Arc, Mutex};
+//! let messages = Arc::new(Mutex::new(Vec::new()));
+//! let console = Console::new({
+//!     let messages = messages.clone();
+//!     move |level: Level, message: &str| {
+//!         messages.lock().unwrap().push(format!("{}: {}", level, message));
+//!     }
+//! });
+//!
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//! ctx.with(|ctx| {
+//!     console.init(ctx).unwrap();
+//!     ctx.eval::<(), _>("console.warn('%s is %d years old', 'Bob', 42)").unwrap();
+//! });
+//! assert_eq!(messages.lock().unwrap()[0], "warn: Bob is 42 years old");
+//! ```
+
+use crate::{
+    Coerced, Ctx, Function, InspectOptions, Mut, Object, ParallelSend, Ref, Rest, Result,
+    StdString, Type, Value,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    time::Instant,
+};
+
+/// The level of console message
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    /// `console.log`, `console.table`, `console.dir`, `console.count`, `console.time*`, `console.group`
+    Log,
+    /// `console.info`
+    Info,
+    /// `console.warn`
+    Warn,
+    /// `console.error`, `console.assert`
+    Error,
+    /// `console.debug`
+    Debug,
+    /// `console.trace`
+    Trace,
+}
+
+impl Level {
+    /// Returns string representation of level
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Log => "log",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+impl AsRef<str> for Level {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// The receiver of console messages
+///
+/// The messages is already formatted and indented according to the current group.
+/// Any `FnMut(Level, &str)` closure can be used as sink.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+pub trait Sink: ParallelSend + 'static {
+    /// Write formatted message
+    fn write(&mut self, level: Level, message: &str);
+}
+
+impl<F> Sink for F
+where
+    F: FnMut(Level, &str) + ParallelSend + 'static,
+{
+    fn write(&mut self, level: Level, message: &str) {
+        self(level, message)
+    }
+}
+
+/// The sink which writes messages to stdout and stderr
+///
+/// The `warn`, `error` and `trace` messages is written to stderr like Node does.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdSink;
+
+impl Sink for StdSink {
+    fn write(&mut self, level: Level, message: &str) {
+        match level {
+            Level::Warn | Level::Error | Level::Trace => eprintln!("{}", message),
+            _ => println!("{}", message),
+        }
+    }
+}
+
+/// The sink which writes messages using [`log`] crate
+///
+/// The `log` and `info` messages is written with [`log::Level::Info`].
+#[cfg(feature = "log")]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(all(feature = "console", feature = "log")))
+)]
+#[derive(Debug, Clone)]
+pub struct LogSink {
+    target: StdString,
+}
+
+#[cfg(feature = "log")]
+impl LogSink {
+    /// Create sink which writes messages with the given target
+    pub fn new<T: Into<StdString>>(target: T) -> Self {
+        Self {
+            target: target.into(),
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl Default for LogSink {
+    fn default() -> Self {
+        Self::new("console")
+    }
+}
+
+#[cfg(feature = "log")]
+impl Sink for LogSink {
+    fn write(&mut self, level: Level, message: &str) {
+        let level = match level {
+            Level::Log | Level::Info => log::Level::Info,
+            Level::Warn => log::Level::Warn,
+            Level::Error => log::Level::Error,
+            Level::Debug => log::Level::Debug,
+            Level::Trace => log::Level::Trace,
+        };
+        log::log!(target: &self.target, level, "{}", message);
+    }
+}
+
+/// The sink which emits messages as [`tracing`] events with `console` target
+///
+/// The `log` and `info` messages is emitted with [`tracing::Level::INFO`].
+#[cfg(feature = "tracing")]
+#[cfg_attr(
+    feature = "doc-cfg",
+    doc(cfg(all(feature = "console", feature = "tracing")))
+)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl Sink for TracingSink {
+    fn write(&mut self, level: Level, message: &str) {
+        match level {
+            Level::Log | Level::Info => tracing::info!(target: "console", "{}", message),
+            Level::Warn => tracing::warn!(target: "console", "{}", message),
+            Level::Error => tracing::error!(target: "console", "{}", message),
+            Level::Debug => tracing::debug!(target: "console", "{}", message),
+            Level::Trace => tracing::trace!(target: "console", "{}", message),
+        }
+    }
+}
+
+struct State {
+    sink: Box<dyn Sink>,
+    group: usize,
+    counts: HashMap<StdString, usize>,
+    timers: HashMap<StdString, Instant>,
+}
+
+impl State {
+    fn write(&mut self, level: Level, message: &str) {
+        if self.group == 0 {
+            self.sink.write(level, message);
+        } else {
+            let indent = "  ".repeat(self.group);
+            let message = message
+                .lines()
+                .map(|line| format!("{}{}", indent, line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.sink.write(level, &message);
+        }
+    }
+}
+
+/// The `console` built-in
+///
+/// The console state (counters, timers and groups) is shared between all contexts where it is installed.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+#[derive(Clone)]
+pub struct Console {
+    state: Ref<Mut<State>>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new(StdSink)
+    }
+}
+
+impl Console {
+    /// Create console which writes messages to the sink
+    pub fn new<S: Sink>(sink: S) -> Self {
+        Self {
+            state: Ref::new(Mut::new(State {
+                sink: Box::new(sink),
+                group: 0,
+                counts: HashMap::new(),
+                timers: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Install the global `console` object into the context
+    pub fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        ctx.globals().set("console", self.object(ctx)?)
+    }
+
+    /// Create the `console` object
+    pub fn object<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let object = Object::new(ctx)?;
+
+        for (name, level) in &[
+            ("log", Level::Log),
+            ("info", Level::Info),
+            ("warn", Level::Warn),
+            ("error", Level::Error),
+            ("debug", Level::Debug),
+        ] {
+            let level = *level;
+            self.method(ctx, &object, name, move |state, ctx, args| {
+                let message = format(ctx, &args)?;
+                state.lock().write(level, &message);
+                Ok(())
+            })?;
+        }
+
+        self.method(ctx, &object, "trace", |state, ctx, args| {
+            let error: Function = ctx.globals().get("Error")?;
+            let error: Object = error.construct(())?;
+            let stack: StdString = error
+                .get::<_, Option<StdString>>("stack")?
+                .unwrap_or_default();
+            let message = format(ctx, &args)?;
+            let message = if message.is_empty() {
+                format!("Trace\n{}", stack.trim_end())
+            } else {
+                format!("Trace: {}\n{}", message, stack.trim_end())
+            };
+            state.lock().write(Level::Trace, &message);
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "dir", |state, _ctx, args| {
+            if let Some(value) = args.first() {
+                let message = value.inspect(InspectOptions::default())?;
+                state.lock().write(Level::Log, &message);
+            }
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "assert", |state, ctx, args| {
+            let mut args = args.into_iter();
+            let condition = args
+                .next()
+                .map(|value| value.get::<Coerced<bool>>())
+                .transpose()?
+                .map(|value| value.0)
+                .unwrap_or(false);
+            if !condition {
+                let message = format(ctx, &args.collect::<Vec<_>>())?;
+                let message = if message.is_empty() {
+                    "Assertion failed".into()
+                } else {
+                    format!("Assertion failed: {}", message)
+                };
+                state.lock().write(Level::Error, &message);
+            }
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "count", |state, _ctx, args| {
+            let label = label(&args)?;
+            let mut state = state.lock();
+            let count = state.counts.entry(label.clone()).or_insert(0);
+            *count += 1;
+            let message = format!("{}: {}", label, count);
+            state.write(Level::Log, &message);
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "countReset", |state, _ctx, args| {
+            let label = label(&args)?;
+            let mut state = state.lock();
+            if state.counts.remove(&label).is_none() {
+                let message = format!("Count for '{}' does not exist", label);
+                state.write(Level::Warn, &message);
+            }
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "time", |state, _ctx, args| {
+            let label = label(&args)?;
+            let mut state = state.lock();
+            if let Entry::Vacant(entry) = state.timers.entry(label.clone()) {
+                entry.insert(Instant::now());
+            } else {
+                let message = format!("Label '{}' already exists for console.time()", label);
+                state.write(Level::Warn, &message);
+            }
+            Ok(())
+        })?;
+
+        for (name, remove) in &[("timeLog", false), ("timeEnd", true)] {
+            let (name, remove) = (*name, *remove);
+            self.method(ctx, &object, name, move |state, ctx, args| {
+                let label = label(&args)?;
+                let extra = format(ctx, args.get(1..).unwrap_or_default())?;
+                let mut state = state.lock();
+                let start = if remove {
+                    state.timers.remove(&label)
+                } else {
+                    state.timers.get(&label).copied()
+                };
+                let message = match start {
+                    Some(start) => {
+                        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+                        let message = format!("{}: {:.3}ms", label, elapsed);
+                        if extra.is_empty() {
+                            message
+                        } else {
+                            format!("{} {}", message, extra)
+                        }
+                    }
+                    None => {
+                        let message = format!("No such label '{}' for console.{}()", label, name);
+                        state.write(Level::Warn, &message);
+                        return Ok(());
+                    }
+                };
+                state.write(Level::Log, &message);
+                Ok(())
+            })?;
+        }
+
+        for name in &["group", "groupCollapsed"] {
+            self.method(ctx, &object, name, |state, ctx, args| {
+                let message = format(ctx, &args)?;
+                let mut state = state.lock();
+                if !message.is_empty() {
+                    state.write(Level::Log, &message);
+                }
+                state.group += 1;
+                Ok(())
+            })?;
+        }
+
+        self.method(ctx, &object, "groupEnd", |state, _ctx, _args| {
+            let mut state = state.lock();
+            state.group = state.group.saturating_sub(1);
+            Ok(())
+        })?;
+
+        self.method(ctx, &object, "table", |state, ctx, args| {
+            let message = match args.first() {
+                Some(data) if data.is_object() && !data.is_function() => {
+                    let columns = args
+                        .get(1)
+                        .filter(|columns| columns.is_array())
+                        .map(|columns| columns.get::<Vec<StdString>>())
+                        .transpose()?;
+                    table(data.as_object().unwrap(), columns)?
+                }
+                _ => format(ctx, &args)?,
+            };
+            state.lock().write(Level::Log, &message);
+            Ok(())
+        })?;
+
+        Ok(object)
+    }
+
+    fn method<'js, F>(&self, ctx: Ctx<'js>, object: &Object<'js>, name: &str, func: F) -> Result<()>
+    where
+        F: for<'js_> Fn(&Ref<Mut<State>>, Ctx<'js_>, Vec<Value<'js_>>) -> Result<()>
+            + ParallelSend
+            + 'static,
+    {
+        let state = self.state.clone();
+        let func = Function::new(
+            ctx,
+            js_fn(move |ctx, args: Rest<Value>| func(&state, ctx, args.0)),
+        )?;
+        func.set_name(name)?;
+        object.set(name, func)
+    }
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn js_fn<F>(func: F) -> F
+where
+    F: for<'js> Fn(Ctx<'js>, Rest<Value<'js>>) -> Result<()>,
+{
+    func
+}
+
+/// Get the label from the first argument
+fn label<'js>(args: &[Value<'js>]) -> Result<StdString> {
+    Ok(match args.first() {
+        Some(value) if value.type_of() != Type::Undefined => value.get::<Coerced<StdString>>()?.0,
+        _ => "default".into(),
+    })
+}
+
+/// Format the arguments like `console.log` does
+///
+/// The first argument may contain the substitutions:
+/// - `%s` converts argument to string
+/// - `%d`, `%i` converts argument to integer number
+/// - `%f` converts argument to floating-point number
+/// - `%o` inspects argument with hidden properties
+/// - `%O` inspects argument
+/// - `%c` ignores argument (CSS styles)
+/// - `%%` outputs percent sign
+///
+/// The rest arguments is concatenated separated by spaces.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+pub fn format<'js>(ctx: Ctx<'js>, args: &[Value<'js>]) -> Result<StdString> {
+    let mut out = StdString::new();
+    let mut args = args.iter();
+
+    if let Some(first) = args.as_slice().first().filter(|value| value.is_string()) {
+        let first: StdString = first.get()?;
+        args.next();
+        let mut chars = first.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let spec = match chars.peek() {
+                Some(spec) => *spec,
+                None => {
+                    out.push(c);
+                    break;
+                }
+            };
+            if spec == '%' {
+                chars.next();
+                out.push('%');
+                continue;
+            }
+            if !"sdifoOc".contains(spec) {
+                out.push(c);
+                continue;
+            }
+            let arg = match args.next() {
+                Some(arg) => arg,
+                None => {
+                    out.push(c);
+                    continue;
+                }
+            };
+            chars.next();
+            match spec {
+                's' => out.push_str(&to_string(arg)?),
+                'd' | 'i' => out.push_str(&to_integer(ctx, arg)?),
+                'f' => out.push_str(&to_number(ctx, arg)?.get::<Coerced<StdString>>()?.0),
+                'o' => out.push_str(&arg.inspect(InspectOptions {
+                    show_hidden: true,
+                    depth: Some(4),
+                    ..Default::default()
+                })?),
+                'O' => out.push_str(&arg.inspect(InspectOptions::default())?),
+                _ => {}
+            }
+        }
+    }
+
+    for arg in args {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        if arg.is_string() {
+            out.push_str(&arg.get::<StdString>()?);
+        } else {
+            out.push_str(&arg.inspect(InspectOptions::default())?);
+        }
+    }
+
+    Ok(out)
+}
+
+/// The `%s` conversion
+fn to_string<'js>(value: &Value<'js>) -> Result<StdString> {
+    Ok(match value.type_of() {
+        Type::String => value.get()?,
+        Type::BigInt => format!("{}n", value.get::<Coerced<StdString>>()?.0),
+        Type::Symbol | Type::Object | Type::Array | Type::Function => {
+            value.inspect(InspectOptions {
+                depth: Some(0),
+                ..Default::default()
+            })?
+        }
+        _ => value.inspect(InspectOptions::default())?,
+    })
+}
+
+fn to_number<'js>(ctx: Ctx<'js>, value: &Value<'js>) -> Result<Value<'js>> {
+    if value.is_symbol() {
+        return Ok(Value::new_float(ctx, f64::NAN));
+    }
+    let number: Function = ctx.globals().get("Number")?;
+    number.call((value.clone(),))
+}
+
+/// The `%d` conversion
+fn to_integer<'js>(ctx: Ctx<'js>, value: &Value<'js>) -> Result<StdString> {
+    if value.type_of() == Type::BigInt {
+        return Ok(format!("{}n", value.get::<Coerced<StdString>>()?.0));
+    }
+    let number = to_number(ctx, value)?.get::<f64>()?;
+    Ok(if number.is_finite() {
+        number.trunc().to_string()
+    } else {
+        Value::new_float(ctx, number).get::<Coerced<StdString>>()?.0
+    })
+}
+
+/// Render the data as a table
+fn table<'js>(data: &Object<'js>, columns: Option<Vec<StdString>>) -> Result<StdString> {
+    let cell_options = InspectOptions {
+        depth: Some(0),
+        break_length: usize::MAX,
+        ..Default::default()
+    };
+
+    let mut header = vec!["(index)".to_string()];
+    let mut keys: Vec<StdString> = columns.clone().unwrap_or_default();
+    let mut has_values = false;
+    // [ (index, { column: cell }, value) ]
+    let mut rows = Vec::new();
+
+    for item in data.props::<StdString, Value>() {
+        let (index, value) = item?;
+        let mut cells = HashMap::new();
+        let mut primitive = None;
+        match value.as_object() {
+            Some(row) if !value.is_function() => {
+                for prop in row.props::<StdString, Value>() {
+                    let (key, value) = prop?;
+                    if columns.is_none() && !keys.contains(&key) {
+                        keys.push(key.clone());
+                    }
+                    cells.insert(key, value.inspect(cell_options)?);
+                }
+            }
+            _ => {
+                has_values = true;
+                primitive = Some(value.inspect(cell_options)?);
+            }
+        }
+        rows.push((index, cells, primitive));
+    }
+
+    header.extend(keys.iter().cloned());
+    if has_values {
+        header.push("Values".into());
+    }
+
+    let table = rows
+        .into_iter()
+        .map(|(index, mut cells, primitive)| {
+            let mut row = vec![index];
+            row.extend(keys.iter().map(|key| cells.remove(key).unwrap_or_default()));
+            if has_values {
+                row.push(primitive.unwrap_or_default());
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let widths = (0..header.len())
+        .map(|column| {
+            table
+                .iter()
+                .map(|row| row[column].chars().count())
+                .chain(Some(header[column].chars().count()))
+                .max()
+                .unwrap_or(0)
+                + 2
+        })
+        .collect::<Vec<_>>();
+
+    let line = |left: &str, middle: &str, right: &str| {
+        let cells = widths
+            .iter()
+            .map(|width| "─".repeat(*width))
+            .collect::<Vec<_>>()
+            .join(middle);
+        format!("{}{}{}", left, cells, right)
+    };
+    let row = |cells: &[StdString]| {
+        let cells = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                let len = cell.chars().count();
+                let left = (width - len) / 2;
+                format!(
+                    "{}{}{}",
+                    " ".repeat(left),
+                    cell,
+                    " ".repeat(width - len - left)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("│");
+        format!("│{}│", cells)
+    };
+
+    let mut lines = vec![line("┌", "┬", "┐"), row(&header), line("├", "┼", "┤")];
+    lines.extend(table.iter().map(|cells| row(cells)));
+    lines.push(line("└", "┴", "┘"));
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    fn run(source: &str) -> Vec<(Level, StdString)> {
+        let messages = Ref::new(Mut::new(Vec::new()));
+        let console = Console::new({
+            let messages = messages.clone();
+            move |level: Level, message: &str| messages.lock().push((level, message.to_string()))
+        });
+        test_with(|ctx| {
+            console.init(ctx).unwrap();
+            let _: Value = ctx.eval(source).unwrap();
+        });
+        let messages = messages.lock().clone();
+        messages
+    }
+
+    fn log(message: &str) -> (Level, StdString) {
+        (Level::Log, message.into())
+    }
+
+    #[test]
+    fn substitutions() {
+        assert_eq!(
+            run(r#"
+                console.log('%s: %d%% (%i) %f', 'a', 42.5, '7', '1.5');
+                console.log('%o %O', { a: 1 }, [1]);
+                console.log('%c%s', 'color: red', 'styled', 'rest', 1, { b: 'c' });
+                console.log('%s %d', 'missing');
+                console.info(1, 'a');
+            "#),
+            vec![
+                log("a: 42% (7) 1.5"),
+                log("{ a: 1 } [ 1 ]"),
+                log("styled rest 1 { b: 'c' }"),
+                log("missing %d"),
+                (Level::Info, "1 a".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn methods() {
+        assert_eq!(
+            run(r#"
+                console.count(); console.count(); console.count('a');
+                console.countReset(); console.count();
+                console.assert(true, 'never');
+                console.assert(false, 'x = %d', 1);
+                console.group('Group');
+                console.warn('nested\nlines');
+                console.groupEnd();
+                console.log('end');
+            "#),
+            vec![
+                log("default: 1"),
+                log("default: 2"),
+                log("a: 1"),
+                log("default: 1"),
+                (Level::Error, "Assertion failed: x = 1".into()),
+                log("Group"),
+                (Level::Warn, "  nested\n  lines".into()),
+                log("end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn timers() {
+        let messages = run("console.time('t'); console.timeEnd('t'); console.timeEnd('t');");
+        assert_eq!(messages[0].0, Level::Log);
+        assert!(messages[0].1.starts_with("t: ") && messages[0].1.ends_with("ms"));
+        assert_eq!(
+            messages[1],
+            (
+                Level::Warn,
+                "No such label 't' for console.timeEnd()".into()
+            )
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let messages = run("function f() { console.trace('here'); } f();");
+        assert_eq!(messages[0].0, Level::Trace);
+        assert!(messages[0].1.starts_with("Trace: here\n"));
+        assert!(messages[0].1.contains("at f"));
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            run("console.table([{ a: 1, b: 'x' }, { a: 2 }, 3])"),
+            vec![log(concat!(
+                "┌─────────┬───┬─────┬────────┐\n",
+                "│ (index) │ a │  b  │ Values │\n",
+                "├─────────┼───┼─────┼────────┤\n",
+                "│    0    │ 1 │ 'x' │        │\n",
+                "│    1    │ 2 │     │        │\n",
+                "│    2    │   │     │   3    │\n",
+                "└─────────┴───┴─────┴────────┘",
+            ))]
+        );
+    }
+}
@@ -2,9 +2,17 @@ use crate::{qjs, Error, Result, Runtime};
 use std::{mem, ptr::NonNull};
 
 mod builder;
-pub use builder::{intrinsic, ContextBuilder, Intrinsic};
+pub use builder::{intrinsic, ContextBuilder, Intrinsic, SafeIntrinsic};
 mod ctx;
 pub use ctx::{Ctx, EvalOptions};
+mod deterministic;
+#[cfg(feature = "crypto")]
+pub(crate) use deterministic::seeded_random;
+pub use deterministic::{Clock, Deterministic, VirtualClock};
+mod lockdown;
+pub use lockdown::Lockdown;
+mod template;
+pub use template::ContextTemplate;
 mod multi_with_impl;
 
 /// A trait for using multiple contexts at the same time.
@@ -54,15 +62,17 @@ impl Context {
     /// If additional functions are required use [`Context::custom`],
     /// [`Context::builder`] or [`Context::full`].
     pub fn custom<I: Intrinsic>(runtime: &Runtime) -> Result<Self> {
-        let guard = runtime.inner.lock();
+        let mut guard = runtime.inner.lock();
         let ctx = NonNull::new(unsafe { qjs::JS_NewContextRaw(guard.rt.as_ptr()) })
             .ok_or_else(|| Error::Allocation)?;
+        guard.drop_context_data(ctx);
         unsafe { I::add_intrinsic(ctx) };
         let res = Context {
             ctx,
             rt: runtime.clone(),
         };
         mem::drop(guard);
+        res.with(|ctx| I::init_intrinsic(ctx))?;
 
         Ok(res)
     }
@@ -71,9 +81,10 @@ impl Context {
     /// If precise controll is required of which functions are available use
     /// [`Context::custom`] or [`Context::builder`].
     pub fn full(runtime: &Runtime) -> Result<Self> {
-        let guard = runtime.inner.lock();
+        let mut guard = runtime.inner.lock();
         let ctx = NonNull::new(unsafe { qjs::JS_NewContext(guard.rt.as_ptr()) })
             .ok_or_else(|| Error::Allocation)?;
+        guard.drop_context_data(ctx);
         let res = Context {
             ctx,
             rt: runtime.clone(),
@@ -153,7 +164,7 @@ impl Context {
 impl Drop for Context {
     fn drop(&mut self) {
         //TODO
-        let guard = match self.rt.inner.try_lock() {
+        let mut guard = match self.rt.inner.try_lock() {
             Some(x) => x,
             None => {
                 let p = unsafe { &mut *(self.ctx.as_ptr() as *mut qjs::JSRefCountHeader) };
@@ -169,6 +180,10 @@ impl Drop for Context {
             }
         };
         guard.update_stack_top();
+        let p = unsafe { &*(self.ctx.as_ptr() as *const qjs::JSRefCountHeader) };
+        if p.ref_count <= 1 {
+            guard.drop_context_data(self.ctx);
+        }
         unsafe { qjs::JS_FreeContext(self.ctx.as_ptr()) }
         // Explicitly drop the guard to ensure it is valid during the entire use of runtime
         mem::drop(guard);
@@ -1,4 +1,5 @@
-use crate::{qjs, Context, Result, Runtime};
+use super::template::TemplateSteps;
+use crate::{qjs, Context, ContextTemplate, Ctx, Deterministic, Lockdown, Result, Runtime};
 use std::{marker::PhantomData, ptr::NonNull};
 
 /// The internal trait to add JS builting
@@ -6,10 +7,58 @@ pub trait Intrinsic {
     /// # Safety
     /// Do not need implement it yourself instead you may use predefined intrinsics from [`intrinsic`] module.
     unsafe fn add_intrinsic(ctx: NonNull<qjs::JSContext>);
+
+    /// Initialize intrinsic using safe API
+    ///
+    /// This is called after [`Intrinsic::add_intrinsic`] and does nothing by default.
+    fn init_intrinsic<'js>(_ctx: Ctx<'js>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The safe variant of [`Intrinsic`]
+///
+/// It allows to make host APIs the reusable building blocks of contexts.
+/// The safe intrinsics can be combined with the builtin ones using tuples.
+///
+/// ```
+/// # use rquickjs::{Runtime, Context, Ctx, Func, Result, SafeIntrinsic, intrinsic};
+/// struct Greet;
+///
+/// impl SafeIntrinsic for Greet {
+///     fn init<'js>(ctx: Ctx<'js>) -> Result<()> {
+///         ctx.globals().set("greet", Func::new("greet", |name: String| format!("Hello, {}!", name)))
+///     }
+/// }
+///
+/// let rt = Runtime::new().unwrap();
+/// let ctx = Context::builder()
+///     .with::<(intrinsic::Base, intrinsic::Eval, Greet)>()
+///     .build(&rt)
+///     .unwrap();
+/// let greeting: String = ctx.with(|ctx| ctx.eval("greet('world')").unwrap());
+/// assert_eq!(greeting, "Hello, world!");
+/// ```
+pub trait SafeIntrinsic {
+    /// Add intrinsic to context
+    fn init<'js>(ctx: Ctx<'js>) -> Result<()>;
+}
+
+impl<T: SafeIntrinsic> Intrinsic for T {
+    unsafe fn add_intrinsic(_ctx: NonNull<qjs::JSContext>) {}
+
+    fn init_intrinsic<'js>(ctx: Ctx<'js>) -> Result<()> {
+        <T as SafeIntrinsic>::init(ctx)
+    }
 }
 
 /// Used for building a [`Context`](struct.Context.html) with a specific set of intrinsics
-pub struct ContextBuilder<I>(PhantomData<I>);
+pub struct ContextBuilder<I> {
+    intrinsics: PhantomData<I>,
+    deterministic: Option<Deterministic>,
+    lockdown: Option<Lockdown>,
+    template: Option<TemplateSteps>,
+}
 
 macro_rules! intrinsic_impls {
     (@builtin: $($(#[$meta:meta])* $name:ident $func:ident $(($($args:expr),*))*,)*) => {
@@ -34,6 +83,11 @@ macro_rules! intrinsic_impls {
                 unsafe fn add_intrinsic(_ctx: NonNull<qjs::JSContext>) {
                     $($name::add_intrinsic(_ctx);)*
                 }
+
+                fn init_intrinsic<'js>(_ctx: Ctx<'js>) -> Result<()> {
+                    $($name::init_intrinsic(_ctx)?;)*
+                    Ok(())
+                }
             }
         )*
     }
@@ -132,16 +186,117 @@ intrinsic_impls! {
 
 impl Default for ContextBuilder<()> {
     fn default() -> Self {
-        ContextBuilder(PhantomData)
+        ContextBuilder {
+            intrinsics: PhantomData,
+            deterministic: None,
+            lockdown: None,
+            template: None,
+        }
     }
 }
 
 impl<I: Intrinsic> ContextBuilder<I> {
+    /// Create a builder for contexts which is set up using the template
+    ///
+    /// The steps of template is run after the intrinsics is added and the context is made deterministic.
+    /// See [`ContextTemplate`] for details.
+    pub fn from_template(template: &ContextTemplate<I>) -> Self {
+        ContextBuilder {
+            intrinsics: PhantomData,
+            deterministic: None,
+            lockdown: None,
+            template: Some(template.steps()),
+        }
+    }
+
     pub fn with<J: Intrinsic>(self) -> ContextBuilder<(I, J)> {
-        ContextBuilder(PhantomData)
+        ContextBuilder {
+            intrinsics: PhantomData,
+            deterministic: self.deterministic,
+            lockdown: self.lockdown,
+            template: self.template,
+        }
+    }
+
+    /// Make the context deterministic
+    ///
+    /// See [`Deterministic`] for details.
+    pub fn deterministic(mut self, options: Deterministic) -> Self {
+        self.deterministic = Some(options);
+        self
+    }
+
+    /// Lock down the context
+    ///
+    /// See [`Lockdown`] for details.
+    pub fn lockdown(mut self, options: Lockdown) -> Self {
+        self.lockdown = Some(options);
+        self
     }
 
     pub fn build(self, runtime: &Runtime) -> Result<Context> {
-        Context::custom::<I>(runtime)
+        let context = Context::custom::<I>(runtime)?;
+        // the setup of template should see the deterministic built-ins
+        if let Some(deterministic) = self.deterministic {
+            context.with(|ctx| deterministic.apply(ctx))?;
+        }
+        if let Some(template) = self.template {
+            context.with(|ctx| template.apply(ctx))?;
+        }
+        if let Some(lockdown) = self.lockdown {
+            context.with(|ctx| lockdown.apply(ctx))?;
+        }
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    struct Answer;
+
+    impl SafeIntrinsic for Answer {
+        fn init<'js>(ctx: Ctx<'js>) -> Result<()> {
+            ctx.globals().set("answer", 42)
+        }
+    }
+
+    struct Twice;
+
+    impl SafeIntrinsic for Twice {
+        fn init<'js>(ctx: Ctx<'js>) -> Result<()> {
+            let answer: i32 = ctx.globals().get("answer")?;
+            ctx.globals().set("twice", answer * 2)
+        }
+    }
+
+    struct Failing;
+
+    impl SafeIntrinsic for Failing {
+        fn init<'js>(_ctx: Ctx<'js>) -> Result<()> {
+            Err(Error::Unknown)
+        }
+    }
+
+    #[test]
+    fn safe_intrinsics() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::builder()
+            .with::<(intrinsic::Base, intrinsic::Eval)>()
+            .with::<(Answer, Twice)>()
+            .build(&rt)
+            .unwrap();
+        let result: Vec<i32> = ctx.with(|ctx| ctx.eval("[answer, twice]").unwrap());
+        assert_eq!(result, [42, 84]);
+
+        let ctx = Context::custom::<(intrinsic::Base, Answer)>(&rt).unwrap();
+        let answer: i32 = ctx.with(|ctx| ctx.globals().get("answer").unwrap());
+        assert_eq!(answer, 42);
+
+        assert!(Context::builder()
+            .with::<(intrinsic::Base, Failing)>()
+            .build(&rt)
+            .is_err());
     }
 }
@@ -1,15 +1,19 @@
 use crate::{
-    markers::Invariant, qjs, runtime::Opaque, Context, FromJs, Function, Module, Object, Result,
-    Value,
+    markers::Invariant, qjs, runtime::Opaque, Context, FromJs, Function, Module, Object,
+    ParallelSend, Result, StdResult, Value,
 };
 
 #[cfg(feature = "futures")]
 use std::future::Future;
 
-#[cfg(feature = "futures")]
-use crate::ParallelSend;
+#[cfg(feature = "loader")]
+use crate::{
+    loader::{LoaderHolder, ModuleRegistry},
+    ModuleInfo, StdString,
+};
 
 use std::{
+    any::{Any, TypeId},
     ffi::{CStr, CString},
     fs,
     marker::PhantomData,
@@ -29,7 +33,7 @@ pub struct EvalOptions {
 }
 
 impl EvalOptions {
-    fn to_flag(&self) -> i32 {
+    pub(crate) fn to_flag(&self) -> i32 {
         let mut flag = if self.global {
             qjs::JS_EVAL_TYPE_GLOBAL
         } else {
@@ -158,6 +162,56 @@ impl<'js> Ctx<'js> {
         module.eval()
     }
 
+    /// Parse JSON text into a value
+    pub fn json_parse<V: FromJs<'js>, S: Into<Vec<u8>>>(self, json: S) -> Result<V> {
+        let src = json.into();
+        let len = src.len();
+        // QuickJS expects the NUL terminated buffer
+        let src = CString::new(src)?;
+        let file_name = unsafe { CStr::from_bytes_with_nul_unchecked(b"<json>\0") };
+        V::from_js(self, unsafe {
+            let val = self.handle_exception(qjs::JS_ParseJSON(
+                self.ctx.as_ptr(),
+                src.as_ptr(),
+                len as _,
+                file_name.as_ptr(),
+            ))?;
+            Value::from_js_value(self, val)
+        })
+    }
+
+    /// Resolve module name using the resolver which is set to runtime
+    ///
+    /// The `base` is the name of importing module and `name` is the name of imported module.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+    pub fn resolve_module(self, base: &str, name: &str) -> Result<StdString> {
+        LoaderHolder::resolve(self, base, name)
+    }
+
+    /// Get the modules which is tracked by module loader in this context
+    ///
+    /// See [`Runtime::modules`](crate::Runtime::modules) for details.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+    pub fn modules(self) -> std::vec::IntoIter<ModuleInfo> {
+        let modules = unsafe { self.get_opaque() }
+            .modules
+            .get(&(self.ctx.as_ptr() as usize))
+            .map(|registry| registry.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        modules.into_iter()
+    }
+
+    /// Get the registry of modules of this context
+    #[cfg(feature = "loader")]
+    pub(crate) unsafe fn get_modules(self) -> &'js mut ModuleRegistry {
+        self.get_opaque()
+            .modules
+            .entry(self.ctx.as_ptr() as usize)
+            .or_default()
+    }
+
     /// Returns the global object of this context.
     pub fn globals(self) -> Object<'js> {
         unsafe {
@@ -184,6 +238,40 @@ impl<'js> Ctx<'js> {
         })
     }
 
+    /// Get the user data of given type which is stored in context
+    pub fn userdata<T: Any>(self) -> Option<&'js T> {
+        let key = (self.ctx.as_ptr() as usize, TypeId::of::<T>());
+        unsafe { self.get_opaque() }
+            .context_data
+            .get(&key)
+            .and_then(|data| data.downcast_ref())
+    }
+
+    /// Store the user data in context
+    ///
+    /// The data is keyed by its type and dropped together with context.
+    /// It cannot be replaced because the references to it may be in use
+    /// so the data is given back when the data of same type is already stored.
+    pub fn store_userdata<T: Any + ParallelSend>(self, data: T) -> StdResult<(), T> {
+        let key = (self.ctx.as_ptr() as usize, TypeId::of::<T>());
+        let opaque = unsafe { self.get_opaque() };
+        if opaque.context_data.contains_key(&key) {
+            return Err(data);
+        }
+        opaque.context_data.insert(key, Box::new(data));
+        Ok(())
+    }
+
+    /// Get the user data of given type which is stored in runtime
+    ///
+    /// See [`Runtime::store_userdata`](crate::Runtime::store_userdata).
+    pub fn runtime_userdata<T: Any>(self) -> Option<&'js T> {
+        unsafe { self.get_opaque() }
+            .userdata
+            .get(&TypeId::of::<T>())
+            .and_then(|data| data.downcast_ref())
+    }
+
     pub(crate) unsafe fn get_opaque(self) -> &'js mut Opaque {
         let rt = qjs::JS_GetRuntime(self.ctx.as_ptr());
         &mut *(qjs::JS_GetRuntimeOpaque(rt) as *mut _)
@@ -203,6 +291,55 @@ impl<'js> Ctx<'js> {
 }
 
 mod test {
+    #[test]
+    fn userdata() {
+        use crate::{Context, Ctx, Func, Runtime};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct RequestId(u32);
+        struct Dropped(Arc<AtomicUsize>);
+
+        impl Drop for Dropped {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let runtime = Runtime::new().unwrap();
+        assert!(runtime.store_userdata(String::from("db")).is_ok());
+        assert!(runtime.store_userdata(String::from("other")).is_err());
+        assert_eq!(runtime.userdata::<String>().unwrap(), "db");
+        assert!(runtime.userdata::<u32>().is_none());
+
+        let ctx = Context::full(&runtime).unwrap();
+        let other = Context::full(&runtime).unwrap();
+        ctx.with(|ctx| {
+            assert!(ctx.store_userdata(RequestId(7)).is_ok());
+            assert!(ctx.store_userdata(RequestId(8)).is_err());
+            assert!(ctx.store_userdata(Dropped(drops.clone())).is_ok());
+            let info = Func::new("info", |ctx: Ctx| {
+                let id = ctx.userdata::<RequestId>().map(|id| id.0);
+                let db = ctx.runtime_userdata::<String>().cloned();
+                format!("{:?} {:?}", id, db)
+            });
+            ctx.globals().set("info", info).unwrap();
+            let info: String = ctx.eval("info()").unwrap();
+            assert_eq!(info, r#"Some(7) Some("db")"#);
+        });
+        other.with(|ctx| {
+            assert!(ctx.userdata::<RequestId>().is_none());
+            assert_eq!(ctx.runtime_userdata::<String>().unwrap(), "db");
+        });
+
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop((ctx, other, runtime));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
     #[cfg(feature = "exports")]
     #[test]
     fn exports() {
@@ -0,0 +1,498 @@
+use crate::{
+    Coerced, Ctx, Func, Function, IntoJs, Mut, Object, ParallelSend, Ref, Rest, Result, StdString,
+    This, Value,
+};
+
+/// The source of current time for deterministic contexts
+///
+/// The time is given in milliseconds since the Unix epoch like `Date.now()` returns it.
+/// This trait is implemented for functions which returns `f64`.
+pub trait Clock: ParallelSend + 'static {
+    /// Get the current time
+    fn now(&self) -> f64;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> f64 + ParallelSend + 'static,
+{
+    fn now(&self) -> f64 {
+        self()
+    }
+}
+
+/// The clock which is advanced by host only
+///
+/// The clones of clock shares the same time.
+#[derive(Clone, Default)]
+pub struct VirtualClock(Ref<Mut<f64>>);
+
+impl VirtualClock {
+    /// Create clock which starts at the given time
+    pub fn new(time: f64) -> Self {
+        Self(Ref::new(Mut::new(time)))
+    }
+
+    /// Get the current time
+    pub fn now(&self) -> f64 {
+        *self.0.lock()
+    }
+
+    /// Set the current time
+    pub fn set(&self, time: f64) {
+        *self.0.lock() = time;
+    }
+
+    /// Advance the current time
+    pub fn advance(&self, duration: f64) {
+        *self.0.lock() += duration;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> f64 {
+        VirtualClock::now(self)
+    }
+}
+
+type SharedClock = Ref<Mut<Box<dyn Clock>>>;
+
+/// The options of deterministic execution
+///
+/// The contexts which is built with these options behaves identically run to run:
+///
+/// - `Math.random()` produces the same sequence for the same seed.
+/// - `Date.now()`, `new Date()` and `Date()` uses the given [`Clock`] (the time is frozen at the Unix epoch by default).
+/// - The local time of dates is pinned to UTC, so the local getters, setters, string conversions
+///   and the parsing of date strings without offset does not depend on the time zone of host.
+/// - The host built-ins like `crypto.getRandomValues()` takes the random values from the seeded generator too.
+///
+/// The built-ins which is installed separately is not affected:
+///
+/// - The timers should be installed with [`ManualClock`](crate::timers::ManualClock) to get deterministic scheduling.
+/// - The `fetch` and `fs` modules does I/O by its nature so the host should provide deterministic
+///   handlers and files or not install them at all.
+///
+/// ```
+/// # use rquickjs::{Runtime, Context, Deterministic, VirtualClock};
+/// let rt = Runtime::new().unwrap();
+/// let clock = VirtualClock::new(1_000_000.0);
+/// let build = || {
+///     Context::builder()
+///         .with::<rquickjs::intrinsic::All>()
+///         .deterministic(Deterministic::new(42).with_clock(clock.clone()))
+///         .build(&rt)
+///         .unwrap()
+/// };
+/// let random = |ctx: &Context| ctx.with(|ctx| ctx.eval::<f64, _>("Math.random()").unwrap());
+/// assert_eq!(random(&build()), random(&build()));
+///
+/// clock.advance(500.0);
+/// let now: f64 = build().with(|ctx| ctx.eval("new Date().getTime()").unwrap());
+/// assert_eq!(now, 1_000_500.0);
+/// ```
+pub struct Deterministic {
+    seed: u64,
+    clock: Option<Box<dyn Clock>>,
+}
+
+impl Deterministic {
+    /// Create options with the seed of random generator
+    pub fn new(seed: u64) -> Self {
+        Self { seed, clock: None }
+    }
+
+    /// Set the clock which is used by `Date`
+    pub fn set_clock<C: Clock>(&mut self, clock: C) -> &mut Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Set the clock which is used by `Date`
+    #[must_use]
+    pub fn with_clock<C: Clock>(mut self, clock: C) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    pub(crate) fn apply<'js>(self, ctx: Ctx<'js>) -> Result<()> {
+        let random = SeededRandom::new(self.seed);
+        let clock: SharedClock = Ref::new(Mut::new(self.clock.unwrap_or_else(|| Box::new(|| 0.0))));
+        let globals = ctx.globals();
+
+        if let Some(math) = globals.get::<_, Option<Object>>("Math")? {
+            let random = random.clone();
+            math.set("random", Func::new("random", move || random.next_f64()))?;
+        }
+
+        if let Some(date) = globals.get::<_, Option<Function>>("Date")? {
+            pin_utc(ctx, &date.get_prototype()?)?;
+            globals.set("Date", date_shim(ctx, date, clock)?)?;
+        }
+
+        // the data of same type can't be attached twice so the context is fresh here
+        let _ = ctx.store_userdata(random);
+        Ok(())
+    }
+}
+
+/// Get the random generator of deterministic context
+#[cfg(feature = "crypto")]
+pub(crate) fn seeded_random<'js>(ctx: Ctx<'js>) -> Option<&'js SeededRandom> {
+    ctx.userdata::<SeededRandom>()
+}
+
+/// The seeded pseudo-random generator (SplitMix64)
+#[derive(Clone)]
+pub(crate) struct SeededRandom(Ref<Mut<u64>>);
+
+impl SeededRandom {
+    fn new(seed: u64) -> Self {
+        Self(Ref::new(Mut::new(seed)))
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let mut state = self.0.lock();
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Get the number in range `[0, 1)`
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    #[cfg(feature = "crypto")]
+    pub fn fill(&self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+}
+
+/// Create the `Date` constructor which uses the clock to get the current time
+///
+/// The shim is the original `Date` bound to the native function
+/// so the GC can see the reference to the original constructor.
+fn date_shim<'js>(ctx: Ctx<'js>, date: Function<'js>, clock: SharedClock) -> Result<Function<'js>> {
+    let construct = {
+        let clock = clock.clone();
+        Function::new(
+            ctx,
+            date_fn(move |ctx, this, date, args| {
+                let now = clock.lock().now();
+                if this.0.as_function().is_some() {
+                    // called as a constructor so `this` is the `new.target`
+                    let mut args = args.0;
+                    match args.len() {
+                        0 => args.push(now.into_js(ctx)?),
+                        1 => {
+                            if let Some(text) = args[0].as_string() {
+                                let time = parse_utc(&date, text.to_string()?)?;
+                                args[0] = time.into_js(ctx)?;
+                            }
+                        }
+                        _ => {
+                            // the date components is treated as UTC
+                            let utc: Function = date.as_object().get("UTC")?;
+                            let time: f64 = utc.call((Rest(args),))?;
+                            args = vec![time.into_js(ctx)?];
+                        }
+                    }
+                    let construct: Function = ctx
+                        .globals()
+                        .get::<_, Object>("Reflect")?
+                        .get("construct")?;
+                    construct.call((date, args, this.0))
+                } else {
+                    let date: Object = date.construct((now,))?;
+                    let to_string: Function = date.get("toString")?;
+                    to_string.call((This(date),))
+                }
+            }),
+        )?
+    };
+    construct.set_constructor(true);
+    let proto = date.get_prototype()?;
+    construct.set_prototype(&proto);
+
+    let shim = bind(ctx, construct, &date)?;
+    shim.set_name("Date")?;
+    // this sets `Date.prototype.constructor` to shim too
+    shim.set_prototype(&proto);
+    let statics = shim.as_object();
+    statics.set("now", Func::new("now", move || clock.lock().now()))?;
+    let parse = Function::new(
+        ctx,
+        |date: Function, text: Coerced<StdString>| -> Result<f64> { parse_utc(&date, text.0) },
+    )?;
+    let parse = bind(ctx, parse, &date)?;
+    parse.set_name("parse")?;
+    statics.set("parse", parse)?;
+    statics.set("UTC", date.as_object().get::<_, Value>("UTC")?)?;
+    Ok(shim)
+}
+
+/// Bind the original `Date` as the first argument of function
+///
+/// The bound function holds the reference so the GC can see it.
+fn bind<'js>(ctx: Ctx<'js>, func: Function<'js>, date: &Function<'js>) -> Result<Function<'js>> {
+    let bind: Function = func.as_object().get("bind")?;
+    bind.call((This(func), Value::new_undefined(ctx), date.clone()))
+}
+
+/// Parse date string treating the local time as UTC
+///
+/// The strings without offset is parsed with `Z` suffix first to get the UTC time.
+fn parse_utc<'js>(date: &Function<'js>, text: StdString) -> Result<f64> {
+    let parse: Function = date.as_object().get("parse")?;
+    let time: f64 = parse.call((format!("{}Z", text),))?;
+    if !time.is_nan() {
+        return Ok(time);
+    }
+    parse.call((text,))
+}
+
+/// The local time methods which is replaced by UTC ones
+const UTC_METHODS: &[(&str, &str)] = &[
+    ("getFullYear", "getUTCFullYear"),
+    ("getMonth", "getUTCMonth"),
+    ("getDate", "getUTCDate"),
+    ("getDay", "getUTCDay"),
+    ("getHours", "getUTCHours"),
+    ("getMinutes", "getUTCMinutes"),
+    ("getSeconds", "getUTCSeconds"),
+    ("getMilliseconds", "getUTCMilliseconds"),
+    ("setFullYear", "setUTCFullYear"),
+    ("setMonth", "setUTCMonth"),
+    ("setDate", "setUTCDate"),
+    ("setHours", "setUTCHours"),
+    ("setMinutes", "setUTCMinutes"),
+    ("setSeconds", "setUTCSeconds"),
+    ("setMilliseconds", "setUTCMilliseconds"),
+];
+
+/// The formats of local date strings
+#[derive(Clone, Copy)]
+enum DateFormat {
+    Full,
+    Date,
+    Time,
+    Locale,
+    LocaleDate,
+    LocaleTime,
+}
+
+/// Pin the local time of `Date` prototype to UTC
+fn pin_utc<'js>(ctx: Ctx<'js>, proto: &Object<'js>) -> Result<()> {
+    for (local, utc) in UTC_METHODS {
+        if let Some(method) = proto.get::<_, Option<Function>>(*utc)? {
+            proto.set(*local, method)?;
+        }
+    }
+    for &(name, format) in &[
+        ("toString", DateFormat::Full),
+        ("toDateString", DateFormat::Date),
+        ("toTimeString", DateFormat::Time),
+        ("toLocaleString", DateFormat::Locale),
+        ("toLocaleDateString", DateFormat::LocaleDate),
+        ("toLocaleTimeString", DateFormat::LocaleTime),
+    ] {
+        let method = Function::new(ctx, move |this: This<Object>| date_string(&this.0, format))?;
+        method.set_name(name)?;
+        proto.set(name, method)?;
+    }
+    let offset = Function::new(ctx, |this: This<Object>| -> Result<f64> {
+        let time = call_f64(&this.0, "getTime")?;
+        Ok(if time.is_nan() { time } else { 0.0 })
+    })?;
+    offset.set_name("getTimezoneOffset")?;
+    proto.set("getTimezoneOffset", offset)?;
+    Ok(())
+}
+
+fn call_f64<'js>(date: &Object<'js>, method: &str) -> Result<f64> {
+    let method: Function = date.get(method)?;
+    method.call((This(date.clone()),))
+}
+
+/// Format the date like QuickJS does for the UTC time zone
+fn date_string<'js>(date: &Object<'js>, format: DateFormat) -> Result<StdString> {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    if call_f64(date, "getTime")?.is_nan() {
+        return Ok("Invalid Date".into());
+    }
+    let field = |method| call_f64(date, method).map(|value| value as i64);
+    let year = field("getUTCFullYear")?;
+    let month = field("getUTCMonth")? as usize;
+    let day = field("getUTCDate")?;
+    let week_day = field("getUTCDay")? as usize;
+    let hours = field("getUTCHours")?;
+    let minutes = field("getUTCMinutes")?;
+    let seconds = field("getUTCSeconds")?;
+    let year_width = if year < 0 { 5 } else { 4 };
+
+    let date = format!(
+        "{} {} {:02} {:0width$}",
+        DAYS[week_day],
+        MONTHS[month],
+        day,
+        year,
+        width = year_width
+    );
+    let time = format!("{:02}:{:02}:{:02} GMT+0000", hours, minutes, seconds);
+    let locale_date = format!(
+        "{:02}/{:02}/{:0width$}",
+        month + 1,
+        day,
+        year,
+        width = year_width
+    );
+    let locale_time = format!(
+        "{:02}:{:02}:{:02} {}M",
+        (hours + 11) % 12 + 1,
+        minutes,
+        seconds,
+        if hours < 12 { 'A' } else { 'P' }
+    );
+
+    Ok(match format {
+        DateFormat::Full => format!("{} {}", date, time),
+        DateFormat::Date => date,
+        DateFormat::Time => time,
+        DateFormat::Locale => format!("{}, {}", locale_date, locale_time),
+        DateFormat::LocaleDate => locale_date,
+        DateFormat::LocaleTime => locale_time,
+    })
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn date_fn<F>(func: F) -> F
+where
+    F: for<'js> Fn(
+        Ctx<'js>,
+        This<Value<'js>>,
+        Function<'js>,
+        Rest<Value<'js>>,
+    ) -> Result<Value<'js>>,
+{
+    func
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn build(rt: &Runtime, seed: u64, clock: &VirtualClock) -> Context {
+        Context::builder()
+            .with::<intrinsic::All>()
+            .deterministic(Deterministic::new(seed).with_clock(clock.clone()))
+            .build(rt)
+            .unwrap()
+    }
+
+    #[test]
+    fn random() {
+        let rt = Runtime::new().unwrap();
+        let clock = VirtualClock::default();
+        let sequence = |seed| -> Vec<f64> {
+            build(&rt, seed, &clock).with(|ctx| {
+                ctx.eval("[Math.random(), Math.random(), Math.random()]")
+                    .unwrap()
+            })
+        };
+        let first = sequence(1);
+        assert_eq!(first, sequence(1));
+        assert_ne!(first, sequence(2));
+        assert!(first.iter().all(|x| (0.0..1.0).contains(x)));
+    }
+
+    #[test]
+    fn date() {
+        let rt = Runtime::new().unwrap();
+        let clock = VirtualClock::new(86_400_000.0);
+        let ctx = build(&rt, 0, &clock);
+        let check = |expected: f64| {
+            ctx.with(|ctx| {
+                let result: Vec<f64> = ctx
+                    .eval(
+                        r#"(() => {
+                        class MyDate extends Date {}
+                        return [
+                            Date.now(),
+                            new Date().getTime(),
+                            new MyDate().getTime(),
+                            new Date(5).getTime(),
+                            Date.UTC(1970, 0, 2),
+                            Number(new Date() instanceof Date && new MyDate() instanceof MyDate),
+                            Number(typeof Date() == "string" && new Date().constructor === Date),
+                        ];
+                    })()"#,
+                    )
+                    .unwrap();
+                assert_eq!(
+                    result,
+                    [expected, expected, expected, 5.0, 86_400_000.0, 1.0, 1.0]
+                );
+            })
+        };
+        check(86_400_000.0);
+        clock.advance(1000.0);
+        check(86_401_000.0);
+    }
+
+    #[test]
+    fn utc_time_zone() {
+        let rt = Runtime::new().unwrap();
+        let clock = VirtualClock::new(1_600_000_000_000.0);
+        let ctx = build(&rt, 0, &clock);
+        ctx.with(|ctx| {
+            let result: Vec<StdString> = ctx
+                .eval(
+                    r#"
+                    const now = new Date();
+                    const date = new Date(2020, 1, 3, 4, 5, 6);
+                    date.setHours(7);
+                    [
+                        String(now.getTimezoneOffset()),
+                        [now.getHours(), now.getMinutes(), now.getDay()].join(),
+                        now.toString(),
+                        String(Date()),
+                        now.toDateString(),
+                        now.toLocaleString(),
+                        date.toISOString(),
+                        new Date("2020-02-03T04:05:06").toISOString(),
+                        String(Date.parse("2020-02-03T04:05:06") == Date.UTC(2020, 1, 3, 4, 5, 6)),
+                        new Date("2020-02-03T04:05:06+01:00").toISOString(),
+                        new Date(NaN).toString(),
+                    ]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(
+                result,
+                [
+                    "0",
+                    "12,26,0",
+                    "Sun Sep 13 2020 12:26:40 GMT+0000",
+                    "Sun Sep 13 2020 12:26:40 GMT+0000",
+                    "Sun Sep 13 2020",
+                    "09/13/2020, 12:26:40 PM",
+                    "2020-02-03T07:05:06.000Z",
+                    "2020-02-03T04:05:06.000Z",
+                    "true",
+                    "2020-02-03T03:05:06.000Z",
+                    "Invalid Date",
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,364 @@
+use crate::{Ctx, Error, Filter, Function, IntoJs, Object, Rest, Result, StdString, Value};
+use std::collections::HashSet;
+
+type GlobalInit = Box<dyn for<'js> FnOnce(Ctx<'js>) -> Result<Value<'js>>>;
+
+/// The options of context lockdown
+///
+/// The lockdown is applied after the intrinsics is added to make the context safe for untrusted code:
+///
+/// - The named globals like `eval` is removed.
+/// - The `Function` constructor and the constructors of async and generator functions is disabled
+///   (also when accessed via the `constructor` property of functions).
+/// - The intrinsics and their prototypes is frozen SES-style so the scripts cannot pollute them.
+///   The global object itself stays extensible so the scripts can define own globals.
+/// - The host globals is installed at the end and frozen too when the intrinsics is frozen.
+///
+/// ```
+/// # use rquickjs::{Runtime, Context, Lockdown, intrinsic};
+/// let rt = Runtime::new().unwrap();
+/// let ctx = Context::builder()
+///     .with::<intrinsic::All>()
+///     .lockdown(Lockdown::strict().with_global("version", "1.0"))
+///     .build(&rt)
+///     .unwrap();
+/// ctx.with(|ctx| {
+///     assert!(ctx.eval::<(), _>("Array.prototype.push = null").is_err());
+///     assert!(ctx.eval::<(), _>("Function('return this')").is_err());
+///     assert_eq!(ctx.eval::<String, _>("typeof eval").unwrap(), "undefined");
+///     assert_eq!(ctx.eval::<String, _>("version").unwrap(), "1.0");
+/// });
+/// ```
+#[derive(Default)]
+pub struct Lockdown {
+    remove: Vec<StdString>,
+    disable_function: bool,
+    freeze: bool,
+    globals: Vec<(StdString, GlobalInit)>,
+}
+
+impl Lockdown {
+    /// Create options which removes `eval`, disables `Function` constructor and freezes intrinsics
+    pub fn strict() -> Self {
+        Self::default()
+            .without_global("eval")
+            .without_function_constructor()
+            .with_frozen_intrinsics()
+    }
+
+    /// Remove global
+    pub fn remove_global<N: Into<StdString>>(&mut self, name: N) -> &mut Self {
+        self.remove.push(name.into());
+        self
+    }
+
+    /// Remove global
+    #[must_use]
+    pub fn without_global<N: Into<StdString>>(mut self, name: N) -> Self {
+        self.remove_global(name);
+        self
+    }
+
+    /// Disable the constructors of functions
+    ///
+    /// The constructors is replaced by the functions which always throws.
+    pub fn disable_function_constructor(&mut self) -> &mut Self {
+        self.disable_function = true;
+        self
+    }
+
+    /// Disable the constructors of functions
+    #[must_use]
+    pub fn without_function_constructor(mut self) -> Self {
+        self.disable_function_constructor();
+        self
+    }
+
+    /// Freeze the intrinsics and their prototypes
+    pub fn freeze_intrinsics(&mut self) -> &mut Self {
+        self.freeze = true;
+        self
+    }
+
+    /// Freeze the intrinsics and their prototypes
+    #[must_use]
+    pub fn with_frozen_intrinsics(mut self) -> Self {
+        self.freeze_intrinsics();
+        self
+    }
+
+    /// Add host global with value produced by function
+    pub fn add_global_fn<N, F>(&mut self, name: N, init: F) -> &mut Self
+    where
+        N: Into<StdString>,
+        F: for<'js> FnOnce(Ctx<'js>) -> Result<Value<'js>> + 'static,
+    {
+        self.globals.push((name.into(), Box::new(init)));
+        self
+    }
+
+    /// Add host global with value produced by function
+    #[must_use]
+    pub fn with_global_fn<N, F>(mut self, name: N, init: F) -> Self
+    where
+        N: Into<StdString>,
+        F: for<'js> FnOnce(Ctx<'js>) -> Result<Value<'js>> + 'static,
+    {
+        self.add_global_fn(name, init);
+        self
+    }
+
+    /// Add host global
+    pub fn add_global<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<StdString>,
+        V: for<'js> IntoJs<'js> + 'static,
+    {
+        self.add_global_fn(name, |ctx| value.into_js(ctx))
+    }
+
+    /// Add host global
+    #[must_use]
+    pub fn with_global<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<StdString>,
+        V: for<'js> IntoJs<'js> + 'static,
+    {
+        self.add_global(name, value);
+        self
+    }
+
+    pub(crate) fn apply<'js>(self, ctx: Ctx<'js>) -> Result<()> {
+        let globals = ctx.globals();
+        let intrinsics = Intrinsics::new(ctx)?;
+        let hidden = hidden_functions(ctx);
+
+        if self.disable_function {
+            if let Some(function) = globals.get::<_, Option<Function>>("Function")? {
+                globals.set(
+                    "Function",
+                    disabled_constructor(ctx, &function.get_prototype()?)?,
+                )?;
+            }
+            for function in &hidden {
+                // the prototype of function instance is the prototype of its constructor
+                let proto = Object::from_value(intrinsics.prototype_of(function)?)?;
+                disabled_constructor(ctx, &proto)?;
+            }
+        }
+
+        for name in &self.remove {
+            globals.remove(name.as_str())?;
+        }
+
+        if self.freeze {
+            let mut roots = hidden;
+            roots.extend(hidden_iterators(ctx));
+            // the intrinsics is non-enumerable properties of global object
+            for prop in globals.own_props::<Value, Value>(Filter::new().string().symbol()) {
+                roots.push(prop?.1);
+            }
+            intrinsics.harden(globals.as_value(), roots)?;
+        }
+
+        for (name, init) in self.globals {
+            let value = init(ctx)?;
+            if self.freeze {
+                intrinsics.harden(globals.as_value(), vec![value.clone()])?;
+            }
+            globals.set(name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the functions of kinds which constructors isn't reachable from globals
+///
+/// These is available only when the context can compile scripts.
+fn hidden_functions<'js>(ctx: Ctx<'js>) -> Vec<Value<'js>> {
+    ctx.eval::<Vec<Value>, _>("[function* () {}, async function () {}, async function* () {}]")
+        .unwrap_or_default()
+}
+
+/// Get the iterators which prototypes isn't reachable from globals
+///
+/// The prototypes like `%ArrayIteratorPrototype%` is reachable only via the prototypes of iterator instances.
+/// The `%AsyncFromSyncIteratorPrototype%` is never exposed to scripts so it is not needed to be frozen.
+/// Each iterator is created separately because the context may have no some intrinsics.
+fn hidden_iterators<'js>(ctx: Ctx<'js>) -> Vec<Value<'js>> {
+    [
+        "[][Symbol.iterator]()",
+        "new Map().entries()",
+        "new Set().values()",
+        "''[Symbol.iterator]()",
+        "/(?:)/g[Symbol.matchAll]('')",
+    ]
+    .iter()
+    .filter_map(|source| ctx.eval::<Value, _>(*source).ok())
+    .collect()
+}
+
+/// Replace the constructor of functions with the function which always throws
+fn disabled_constructor<'js>(ctx: Ctx<'js>, proto: &Object<'js>) -> Result<Function<'js>> {
+    let constructor = Function::new(ctx, disabled)?;
+    constructor.set_name("Function")?;
+    constructor.set_constructor(true);
+    constructor.set_prototype(proto);
+    Ok(constructor)
+}
+
+fn disabled<'js>(_: Rest<Value<'js>>) -> Result<()> {
+    Err(Error::new_from_js_message(
+        "code",
+        "function",
+        "The function constructor is disabled",
+    ))
+}
+
+/// The intrinsics which is used to walk and freeze objects
+struct Intrinsics<'js> {
+    freeze: Function<'js>,
+    get_prototype_of: Function<'js>,
+    get_own_property_descriptors: Function<'js>,
+    own_keys: Function<'js>,
+}
+
+impl<'js> Intrinsics<'js> {
+    fn new(ctx: Ctx<'js>) -> Result<Self> {
+        let object: Object = ctx.globals().get("Object")?;
+        let reflect: Object = ctx.globals().get("Reflect")?;
+        Ok(Self {
+            freeze: object.get("freeze")?,
+            get_prototype_of: object.get("getPrototypeOf")?,
+            get_own_property_descriptors: object.get("getOwnPropertyDescriptors")?,
+            own_keys: reflect.get("ownKeys")?,
+        })
+    }
+
+    fn prototype_of(&self, value: &Value<'js>) -> Result<Value<'js>> {
+        self.get_prototype_of.call((value.clone(),))
+    }
+
+    /// Freeze the objects and all the objects which is reachable from it via properties and prototypes
+    ///
+    /// The `skip` object is not frozen and not walked (i.e. the global object).
+    fn harden(&self, skip: &Value<'js>, roots: Vec<Value<'js>>) -> Result<()> {
+        let mut visited = HashSet::new();
+        visited.insert(unsafe { skip.get_ptr() } as usize);
+        let mut queue = roots;
+        while let Some(value) = queue.pop() {
+            if !value.is_object() || !visited.insert(unsafe { value.get_ptr() } as usize) {
+                continue;
+            }
+            self.freeze.call::<_, Value>((value.clone(),))?;
+            queue.push(self.prototype_of(&value)?);
+            let descriptors: Object = self.get_own_property_descriptors.call((value.clone(),))?;
+            let keys: Vec<Value> = self.own_keys.call((descriptors.clone(),))?;
+            for key in keys {
+                let descriptor: Object = descriptors.get(key)?;
+                for field in &["value", "get", "set"] {
+                    queue.push(descriptor.get(*field)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn build(lockdown: Lockdown) -> (Runtime, Context) {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::builder()
+            .with::<intrinsic::All>()
+            .lockdown(lockdown)
+            .build(&rt)
+            .unwrap();
+        (rt, ctx)
+    }
+
+    #[test]
+    fn remove_globals() {
+        let (_rt, ctx) = build(
+            Lockdown::default()
+                .without_global("eval")
+                .without_global("Proxy"),
+        );
+        ctx.with(|ctx| {
+            let types: Vec<StdString> = ctx
+                .eval("[typeof eval, typeof Proxy, typeof Function, typeof Object.freeze]")
+                .unwrap();
+            assert_eq!(types, ["undefined", "undefined", "function", "function"]);
+            // the intrinsics isn't frozen by default
+            let _: () = ctx.eval("Array.prototype.polluted = true").unwrap();
+        });
+    }
+
+    #[test]
+    fn disable_function_constructor() {
+        let (_rt, ctx) = build(Lockdown::default().without_function_constructor());
+        ctx.with(|ctx| {
+            let result: Vec<bool> = ctx
+                .eval(
+                    r#"
+                    const throws = (f) => { try { f(); return false; } catch (e) { return true; } };
+                    [
+                        throws(() => Function("return 1")),
+                        throws(() => new Function("return 1")),
+                        throws(() => (function () {}).constructor("return 1")),
+                        throws(() => (function* () {}).constructor("yield 1")),
+                        throws(() => (async function () {}).constructor("return 1")),
+                        throws(() => (async function* () {}).constructor("yield 1")),
+                        (() => {}) instanceof Function,
+                        (function () {}).constructor === Function,
+                    ]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(result, [true, true, true, true, true, true, true, true]);
+        });
+    }
+
+    #[test]
+    fn freeze_intrinsics() {
+        let (_rt, ctx) = build(Lockdown::strict().with_global("answer", 42).with_global_fn(
+            "config",
+            |ctx| {
+                let config = Object::new(ctx)?;
+                config.set("debug", false)?;
+                Ok(config.into_value())
+            },
+        ));
+        ctx.with(|ctx| {
+            let result: Vec<bool> = ctx
+                .eval(
+                    r#"
+                    const throws = (f) => { try { f(); return false; } catch (e) { return true; } };
+                    [
+                        throws(() => { Array.prototype.push = null; }),
+                        throws(() => { Object.prototype.polluted = true; }),
+                        throws(() => { JSON.parse = null; }),
+                        throws(() => { [][Symbol.iterator]().__proto__.next = null; }),
+                        throws(() => { new Map().entries().__proto__.next = null; }),
+                        throws(() => { new Set().values().__proto__.next = null; }),
+                        throws(() => { ''[Symbol.iterator]().__proto__.next = null; }),
+                        throws(() => { /a/g[Symbol.matchAll]('a').__proto__.next = null; }),
+                        throws(() => { Object.getPrototypeOf([][Symbol.iterator]().__proto__).polluted = true; }),
+                        throws(() => { config.debug = true; }),
+                        Object.isFrozen(Object.getPrototypeOf(function* () {})),
+                        Object.isFrozen(Object.getOwnPropertyDescriptor(Object.prototype, "__proto__").get),
+                        Object.isFrozen(Math) && Object.isFrozen(Reflect) && Object.isFrozen(Promise.prototype),
+                        !Object.isFrozen(globalThis),
+                        (globalThis.defined = answer) == 42,
+                        typeof eval == "undefined",
+                    ]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(result, [true; 16]);
+        });
+    }
+}
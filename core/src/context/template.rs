@@ -0,0 +1,253 @@
+use crate::{
+    intrinsic, qjs, Context, Ctx, EvalOptions, Intrinsic, ParallelSend, ParallelSync, Ref, Result,
+    Runtime,
+};
+use std::{ffi::CString, marker::PhantomData, mem::MaybeUninit, slice};
+
+/// The native setup step of context template
+trait TemplateInit: ParallelSend + ParallelSync + 'static {
+    /// Run setup on the context
+    fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()>;
+}
+
+impl<F> TemplateInit for F
+where
+    F: for<'js> Fn(Ctx<'js>) -> Result<()> + ParallelSend + ParallelSync + 'static,
+{
+    fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        self(ctx)
+    }
+}
+
+#[derive(Clone)]
+enum Step {
+    Bytecode(Ref<[u8]>),
+    Native(Ref<dyn TemplateInit>),
+}
+
+/// The prepared setup of contexts
+///
+/// The setup scripts (i.e. polyfills) is compiled to bytecode once when it is added to template
+/// so the contexts which is built from template only loads and runs the bytecode without parsing.
+/// The native setup steps (i.e. registering host classes) is run on each context in the order of adding.
+///
+/// The template is not bound to runtime and can be used with any runtime.
+/// The runtime which is given on adding script is used only to compile it,
+/// the compiling context is created for each added script and is not kept by template.
+/// The clones of template shares the prepared steps.
+///
+/// ```
+/// # use rquickjs::{Runtime, ContextBuilder, ContextTemplate, Func, intrinsic};
+/// let rt = Runtime::new().unwrap();
+/// let template = ContextTemplate::<intrinsic::All>::new()
+///     .with_script(&rt, "polyfill.js", "globalThis.double = x => x * 2;")
+///     .unwrap()
+///     .with_init(|ctx| ctx.globals().set("triple", Func::new("triple", |x: i32| x * 3)));
+///
+/// let ctx = ContextBuilder::from_template(&template).build(&rt).unwrap();
+/// let result: i32 = ctx.with(|ctx| ctx.eval("double(triple(7))").unwrap());
+/// assert_eq!(result, 42);
+/// ```
+pub struct ContextTemplate<I> {
+    steps: Vec<Step>,
+    intrinsics: PhantomData<I>,
+}
+
+impl<I> Clone for ContextTemplate<I> {
+    fn clone(&self) -> Self {
+        Self {
+            steps: self.steps.clone(),
+            intrinsics: PhantomData,
+        }
+    }
+}
+
+impl<I> Default for ContextTemplate<I> {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            intrinsics: PhantomData,
+        }
+    }
+}
+
+impl<I: Intrinsic> ContextTemplate<I> {
+    /// Create empty template
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile the setup script in the given runtime and add it to template
+    pub fn add_script<N, S>(&mut self, runtime: &Runtime, name: N, source: S) -> Result<&mut Self>
+    where
+        N: Into<Vec<u8>>,
+        S: Into<Vec<u8>>,
+    {
+        let name = CString::new(name)?;
+        let flag = EvalOptions::default().to_flag() | qjs::JS_EVAL_FLAG_COMPILE_ONLY as i32;
+        let compiler = Context::custom::<intrinsic::Eval>(runtime)?;
+        let bytecode = compiler.with(|ctx| unsafe {
+            let function = ctx.eval_raw(source, name.as_c_str(), flag)?;
+            let result = write_bytecode(ctx, function);
+            qjs::JS_FreeValue(ctx.as_ptr(), function);
+            result
+        })?;
+        self.steps.push(Step::Bytecode(bytecode.into()));
+        Ok(self)
+    }
+
+    /// Compile the setup script in the given runtime and add it to template
+    pub fn with_script<N, S>(mut self, runtime: &Runtime, name: N, source: S) -> Result<Self>
+    where
+        N: Into<Vec<u8>>,
+        S: Into<Vec<u8>>,
+    {
+        self.add_script(runtime, name, source)?;
+        Ok(self)
+    }
+
+    /// Add the native setup step to template
+    pub fn add_init<F>(&mut self, init: F) -> &mut Self
+    where
+        F: for<'js> Fn(Ctx<'js>) -> Result<()> + ParallelSend + ParallelSync + 'static,
+    {
+        self.steps.push(Step::Native(Ref::new(init)));
+        self
+    }
+
+    /// Add the native setup step to template
+    #[must_use]
+    pub fn with_init<F>(mut self, init: F) -> Self
+    where
+        F: for<'js> Fn(Ctx<'js>) -> Result<()> + ParallelSend + ParallelSync + 'static,
+    {
+        self.add_init(init);
+        self
+    }
+}
+
+impl<I> ContextTemplate<I> {
+    pub(crate) fn steps(&self) -> TemplateSteps {
+        TemplateSteps(self.steps.clone())
+    }
+}
+
+/// The steps of template which is replayed on building context
+pub(crate) struct TemplateSteps(Vec<Step>);
+
+impl TemplateSteps {
+    pub(crate) fn apply<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        for step in &self.0 {
+            match step {
+                Step::Bytecode(bytecode) => unsafe {
+                    let function = ctx.handle_exception(qjs::JS_ReadObject(
+                        ctx.as_ptr(),
+                        bytecode.as_ptr(),
+                        bytecode.len() as _,
+                        qjs::JS_READ_OBJ_BYTECODE as _,
+                    ))?;
+                    // the function is consumed here
+                    let result =
+                        ctx.handle_exception(qjs::JS_EvalFunction(ctx.as_ptr(), function))?;
+                    qjs::JS_FreeValue(ctx.as_ptr(), result);
+                },
+                Step::Native(init) => init.init(ctx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+unsafe fn write_bytecode(ctx: Ctx, function: qjs::JSValue) -> Result<Vec<u8>> {
+    let mut len = MaybeUninit::uninit();
+    let buf = qjs::JS_WriteObject(
+        ctx.as_ptr(),
+        len.as_mut_ptr(),
+        function,
+        qjs::JS_WRITE_OBJ_BYTECODE as _,
+    );
+    if buf.is_null() {
+        return Err(ctx.get_exception());
+    }
+    let bytecode = slice::from_raw_parts(buf, len.assume_init() as _).to_vec();
+    qjs::js_free(ctx.as_ptr(), buf as _);
+    Ok(bytecode)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn template(rt: &Runtime) -> ContextTemplate<intrinsic::All> {
+        ContextTemplate::new()
+            .with_script(
+                rt,
+                "setup.js",
+                r#"
+                class Counter {
+                    constructor() { this.value = 0; }
+                    increment() { return ++this.value; }
+                }
+                globalThis.Counter = Counter;
+                globalThis.counter = new Counter();
+                "#,
+            )
+            .unwrap()
+            .with_init(|ctx| ctx.globals().set("host", Func::new("host", || "native")))
+            .with_script(rt, "after.js", "globalThis.kind = typeof host;")
+            .unwrap()
+    }
+
+    #[test]
+    fn from_template() {
+        // the template does not keep the compiling runtime
+        let template = template(&Runtime::new().unwrap());
+        let rt = Runtime::new().unwrap();
+        let first = ContextBuilder::from_template(&template).build(&rt).unwrap();
+        // the template can be used with other runtimes too
+        let other = Runtime::new().unwrap();
+        let second = ContextBuilder::from_template(&template)
+            .build(&other)
+            .unwrap();
+
+        let count =
+            |ctx: &Context| -> i32 { ctx.with(|ctx| ctx.eval("counter.increment()").unwrap()) };
+        assert_eq!(count(&first), 1);
+        assert_eq!(count(&first), 2);
+        assert_eq!(count(&second), 1);
+
+        let result: Vec<StdString> = second.with(|ctx| {
+            ctx.eval("[host(), kind, typeof new Counter().increment]")
+                .unwrap()
+        });
+        assert_eq!(result, ["native", "function", "function"]);
+    }
+
+    #[test]
+    fn deterministic_setup() {
+        let rt = Runtime::new().unwrap();
+        let template = ContextTemplate::<intrinsic::All>::new()
+            .with_script(&rt, "setup.js", "globalThis.id = Math.random();")
+            .unwrap();
+        let id = || -> f64 {
+            ContextBuilder::from_template(&template)
+                .deterministic(Deterministic::new(7))
+                .build(&rt)
+                .unwrap()
+                .with(|ctx| ctx.eval("id").unwrap())
+        };
+        assert_eq!(id(), id());
+    }
+
+    #[test]
+    fn script_errors() {
+        let rt = Runtime::new().unwrap();
+        let mut template = ContextTemplate::<intrinsic::All>::new();
+        assert!(template.add_script(&rt, "invalid.js", "let = ;").is_err());
+
+        template
+            .add_script(&rt, "throws.js", "throw new Error('setup failed')")
+            .unwrap();
+        assert!(ContextBuilder::from_template(&template).build(&rt).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! The crypto built-in
+//!
+//! The [`Crypto`] installs the `crypto` global object with `getRandomValues`, `randomUUID` and `subtle.digest`
+//! like the browsers and Node do. The random values is taken from the entropy source of operating system
+//! using the [`getrandom`](https://crates.io/crates/getrandom) crate.
+//!
+//! The `subtle.digest` supports `SHA-1`, `SHA-256`, `SHA-384` and `SHA-512` algorithms.
+//! The digest is computed before returning so the promise is already settled.
+//!
+//! NOTE: The errors which should be `DOMException` in browsers is thrown as `TypeError`.
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, crypto::Crypto};
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//! ctx.with(|ctx| {
+//!     Crypto.init(ctx).unwrap();
+//!     let uuid: String = ctx.eval("crypto.randomUUID()").unwrap();
+//!     assert_eq!(uuid.len(), 36);
+//!     let _: rquickjs::Value = ctx.eval(r#"
+//!         crypto.subtle.digest("SHA-256", new Uint8Array(0)).then(digest => {
+//!             globalThis.length = digest.byteLength;
+//!         });
+//!     "#).unwrap();
+//! });
+//! while rt.execute_pending_job().unwrap() {}
+//! let length: usize = ctx.with(|ctx| ctx.eval("length").unwrap());
+//! assert_eq!(length, 32);
+//! ```
+
+use crate::{
+    context::seeded_random, value::BufferClass, ArrayBuffer, Ctx, Error, Func, IntoJs, Object,
+    Result, StdString, Type, Value,
+};
+use sha2::Digest;
+use std::slice;
+
+/// The maximum number of bytes which can be generated by `getRandomValues` at once
+const MAX_RANDOM_BYTES: usize = 65536;
+
+/// The crypto built-in
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "crypto")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crypto;
+
+impl Crypto {
+    /// Install `crypto` into the global object of context
+    pub fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        ctx.globals().set("crypto", self.object(ctx)?)
+    }
+
+    /// Create the `crypto` object
+    pub fn object<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let object = Object::new(ctx)?;
+        object.set(
+            "getRandomValues",
+            Func::new("getRandomValues", get_random_values),
+        )?;
+        object.set("randomUUID", Func::new("randomUUID", random_uuid))?;
+
+        let subtle = Object::new(ctx)?;
+        subtle.set("digest", Func::new("digest", digest))?;
+        object.set("subtle", subtle)?;
+
+        Ok(object)
+    }
+}
+
+/// Fill the buffer with random bytes
+///
+/// The deterministic contexts uses the seeded generator instead of entropy source.
+fn fill_random(ctx: Ctx, bytes: &mut [u8]) -> Result<()> {
+    if let Some(random) = seeded_random(ctx) {
+        random.fill(bytes);
+        return Ok(());
+    }
+    getrandom::getrandom(bytes).map_err(|error| Error::Io(error.into()))
+}
+
+fn get_random_values<'js>(ctx: Ctx<'js>, array: Value<'js>) -> Result<Value<'js>> {
+    let integer = BufferClass::of(&array)
+        .map(BufferClass::is_integer_array)
+        .unwrap_or(false);
+    let raw = if integer {
+        ArrayBuffer::get_typed_raw(&array)
+    } else {
+        None
+    };
+    let (len, _, ptr) = raw.ok_or_else(|| {
+        Error::new_from_js_message(
+            array.type_of().as_str(),
+            "TypedArray",
+            "The array should be an integer-type TypedArray",
+        )
+    })?;
+    if len > MAX_RANDOM_BYTES {
+        return Err(Error::new_from_js_message(
+            "TypedArray",
+            "bytes",
+            format!(
+                "The array byte length of {} exceeds the number of bytes of entropy available via this API ({})",
+                len, MAX_RANDOM_BYTES
+            ),
+        ));
+    }
+    fill_random(ctx, unsafe { slice::from_raw_parts_mut(ptr, len) })?;
+    Ok(array)
+}
+
+fn random_uuid(ctx: Ctx) -> Result<StdString> {
+    let mut bytes = [0u8; 16];
+    fill_random(ctx, &mut bytes)?;
+    // version 4 and RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let mut uuid = StdString::with_capacity(36);
+    for (index, byte) in bytes.iter().enumerate() {
+        if matches!(index, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        uuid.push_str(&format!("{:02x}", byte));
+    }
+    Ok(uuid)
+}
+
+fn digest<'js>(ctx: Ctx<'js>, algorithm: Value<'js>, data: Value<'js>) -> Result<Value<'js>> {
+    let (promise, resolve, reject) = ctx.promise()?;
+    match digest_bytes(algorithm, data).and_then(|digest| ArrayBuffer::new(ctx, digest)) {
+        Ok(digest) => resolve.call::<_, ()>((digest,))?,
+        Err(error) => reject.call::<_, ()>(((&error).into_js(ctx)?,))?,
+    }
+    Ok(promise.into_value())
+}
+
+fn digest_bytes<'js>(algorithm: Value<'js>, data: Value<'js>) -> Result<Vec<u8>> {
+    let name = match algorithm.type_of() {
+        Type::Object => algorithm.as_object().unwrap().get("name")?,
+        _ => algorithm.get::<StdString>()?,
+    };
+    let data = ArrayBuffer::copy_bytes(&data).ok_or_else(|| {
+        Error::new_from_js_message(
+            data.type_of().as_str(),
+            "bytes",
+            "The data should be an ArrayBuffer or an ArrayBufferView",
+        )
+    })?;
+    Ok(match name.to_ascii_uppercase().as_str() {
+        "SHA-1" => sha1::Sha1::digest(&data).to_vec(),
+        "SHA-256" => sha2::Sha256::digest(&data).to_vec(),
+        "SHA-384" => sha2::Sha384::digest(&data).to_vec(),
+        "SHA-512" => sha2::Sha512::digest(&data).to_vec(),
+        _ => {
+            return Err(Error::new_from_js_message(
+                "string",
+                "algorithm",
+                format!("Unrecognized algorithm name: \"{}\"", name),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crypto::*, *};
+
+    #[test]
+    fn random_values() {
+        test_with(|ctx| {
+            Crypto.init(ctx).unwrap();
+            let result: Vec<bool> = ctx
+                .eval(
+                    r#"
+                    const array = new Uint32Array(64);
+                    const throws = (f) => { try { f(); return false; } catch (e) { return true; } };
+                    [
+                        crypto.getRandomValues(array) === array,
+                        array.some(x => x != 0),
+                        crypto.getRandomValues(new BigInt64Array(2)).length == 2,
+                        throws(() => crypto.getRandomValues(new Float64Array(1))),
+                        throws(() => crypto.getRandomValues([1, 2])),
+                        throws(() => crypto.getRandomValues(Object.defineProperty(
+                            new Float32Array(1), Symbol.toStringTag, { value: "Uint32Array" }
+                        ))),
+                        throws(() => crypto.getRandomValues({ [Symbol.toStringTag]: "Uint8Array" })),
+                        throws(() => crypto.getRandomValues(new Uint8Array(65537))),
+                    ]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(result, [true; 8]);
+        });
+    }
+
+    #[test]
+    fn random_uuid() {
+        test_with(|ctx| {
+            Crypto.init(ctx).unwrap();
+            let uuids: Vec<StdString> = ctx
+                .eval("[crypto.randomUUID(), crypto.randomUUID()]")
+                .unwrap();
+            assert_ne!(uuids[0], uuids[1]);
+            for uuid in uuids {
+                let parts = uuid.split('-').map(|part| part.len()).collect::<Vec<_>>();
+                assert_eq!(parts, [8, 4, 4, 4, 12]);
+                assert_eq!(&uuid[14..15], "4");
+                assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+            }
+        });
+    }
+
+    #[test]
+    fn digest() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            Crypto.init(ctx).unwrap();
+            let _: Value = ctx
+                .eval(
+                    r#"
+                    const hex = (buffer) => Array.from(new Uint8Array(buffer), b => b.toString(16).padStart(2, "0")).join("");
+                    const data = Uint8Array.of(0x61, 0x62, 0x63);
+                    globalThis.result = [];
+                    Promise.all([
+                        crypto.subtle.digest("SHA-1", data),
+                        crypto.subtle.digest({ name: "sha-256" }, data.buffer),
+                        crypto.subtle.digest("SHA-384", data),
+                        crypto.subtle.digest("SHA-512", new DataView(data.buffer)),
+                    ]).then(digests => result.push(...digests.map(hex)));
+                    crypto.subtle.digest("MD5", data).catch(error => result.push(error.message));
+                "#,
+                )
+                .unwrap();
+        });
+        while rt.execute_pending_job().unwrap() {}
+        let result: Vec<StdString> = ctx.with(|ctx| ctx.eval("result").unwrap());
+        assert_eq!(result.len(), 5);
+        assert!(result[0].contains("MD5"));
+        assert_eq!(result[1], "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            result[2],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(&result[3][..16], "cb00753f45a35e8b");
+        assert_eq!(&result[4][..16], "ddaf35a193617aba");
+    }
+}
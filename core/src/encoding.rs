@@ -0,0 +1,420 @@
+//! The text encoding built-ins
+//!
+//! The [`Encoding`] installs `TextEncoder` and `TextDecoder` classes and `atob` and `btoa` functions
+//! like the browsers and Node do. The binary data is represented by `ArrayBuffer`s and typed arrays
+//! so the encoded text can be passed to Rust as [`ArrayBuffer`] or [`TypedArray<u8>`].
+//!
+//! The `TextDecoder` supports `utf-8` and `utf-16le` encodings with `fatal` and `ignoreBOM` options.
+//!
+//! NOTE: The errors which should be `RangeError` or `DOMException` in browsers is thrown as `TypeError`.
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, TypedArray, encoding::Encoding};
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//! ctx.with(|ctx| {
+//!     Encoding.init(ctx).unwrap();
+//!     let bytes: TypedArray<u8> = ctx.eval("new TextEncoder().encode('€')").unwrap();
+//!     let bytes: &[u8] = bytes.as_ref();
+//!     assert_eq!(bytes, &[0xe2, 0x82, 0xac]);
+//!     let text: String = ctx.eval("new TextDecoder().decode(Uint8Array.of(0xe2, 0x82, 0xac))").unwrap();
+//!     assert_eq!(text, "€");
+//!     let text: String = ctx.eval("atob(btoa('Hello'))").unwrap();
+//!     assert_eq!(text, "Hello");
+//! });
+//! ```
+
+use crate::{
+    class_def, Accessor, ArrayBuffer, Class, Coerced, Ctx, Error, Func, Method, Object, Opt,
+    Result, StdString, TypedArray, Value,
+};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The text encoding built-ins
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "encoding")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Encoding;
+
+impl Encoding {
+    /// Install `TextEncoder`, `TextDecoder`, `atob` and `btoa` into the global object of context
+    pub fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        let globals = ctx.globals();
+
+        Class::<TextEncoder>::register(ctx)?;
+        globals.set(
+            "TextEncoder",
+            Func::new(
+                "TextEncoder",
+                Class::<TextEncoder>::constructor(|| TextEncoder),
+            ),
+        )?;
+
+        Class::<TextDecoder>::register(ctx)?;
+        globals.set(
+            "TextDecoder",
+            Func::new(
+                "TextDecoder",
+                Class::<TextDecoder>::constructor(TextDecoder::new),
+            ),
+        )?;
+
+        globals.set("atob", Func::new("atob", atob))?;
+        globals.set("btoa", Func::new("btoa", btoa))?;
+
+        Ok(())
+    }
+}
+
+/// The `TextEncoder` class
+///
+/// Always encodes the text into `utf-8`.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "encoding")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextEncoder;
+
+class_def! {
+    TextEncoder (proto) {
+        proto.prop("encoding", Accessor::from(Method(|_: &TextEncoder| "utf-8")))?;
+        proto.set("encode", Func::from(Method(TextEncoder::encode)))?;
+        proto.set("encodeInto", Func::from(Method(TextEncoder::encode_into)))?;
+    }
+}
+
+impl TextEncoder {
+    fn encode<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        input: Opt<Coerced<StdString>>,
+    ) -> Result<TypedArray<'js, u8>> {
+        let input = input.0.map(|input| input.0).unwrap_or_default();
+        TypedArray::new(ctx, input.into_bytes())
+    }
+
+    fn encode_into<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        input: Coerced<StdString>,
+        mut dest: TypedArray<'js, u8>,
+    ) -> Result<Object<'js>> {
+        let dest: &mut [u8] = dest.as_mut();
+        let mut read = 0;
+        let mut written = 0;
+        for c in input.chars() {
+            let len = c.len_utf8();
+            if written + len > dest.len() {
+                break;
+            }
+            c.encode_utf8(&mut dest[written..]);
+            written += len;
+            // the number of UTF-16 code units like JS strings does
+            read += c.len_utf16();
+        }
+        let result = Object::new(ctx)?;
+        result.set("read", read)?;
+        result.set("written", written)?;
+        Ok(result)
+    }
+}
+
+/// The text encodings which is supported by [`TextDecoder`]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "encoding")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// The `utf-8` encoding
+    Utf8,
+    /// The `utf-16le` encoding
+    Utf16Le,
+}
+
+impl TextEncoding {
+    /// Get the encoding by label
+    ///
+    /// The labels is matched case-insensitive with surrounding whitespaces ignored.
+    pub fn from_label(label: &str) -> Option<Self> {
+        Some(
+            match label
+                .trim_matches(|c: char| c.is_ascii_whitespace())
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "unicode-1-1-utf-8" | "unicode11utf8" | "unicode20utf8" | "utf-8" | "utf8"
+                | "x-unicode20utf8" => Self::Utf8,
+                "csunicode" | "iso-10646-ucs-2" | "ucs-2" | "unicode" | "unicodefeff"
+                | "utf-16" | "utf-16le" => Self::Utf16Le,
+                _ => return None,
+            },
+        )
+    }
+
+    /// Get the canonical name of encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+        }
+    }
+
+    /// Decode the bytes into a string
+    ///
+    /// The byte order mark is stripped unless `ignore_bom` is set.
+    /// The malformed data is replaced by `U+FFFD` unless `fatal` is set, otherwise decoding fails.
+    pub fn decode(&self, bytes: &[u8], fatal: bool, ignore_bom: bool) -> Option<StdString> {
+        match self {
+            Self::Utf8 => {
+                let bytes = match bytes {
+                    [0xef, 0xbb, 0xbf, rest @ ..] if !ignore_bom => rest,
+                    _ => bytes,
+                };
+                if fatal {
+                    std::str::from_utf8(bytes).ok().map(|text| text.into())
+                } else {
+                    Some(StdString::from_utf8_lossy(bytes).into_owned())
+                }
+            }
+            Self::Utf16Le => {
+                let bytes = match bytes {
+                    [0xff, 0xfe, rest @ ..] if !ignore_bom => rest,
+                    _ => bytes,
+                };
+                let chunks = bytes.chunks_exact(2);
+                let odd = !chunks.remainder().is_empty();
+                let units = chunks.map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+                let mut text = if fatal {
+                    char::decode_utf16(units)
+                        .collect::<std::result::Result<StdString, _>>()
+                        .ok()?
+                } else {
+                    char::decode_utf16(units)
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect::<StdString>()
+                };
+                if odd {
+                    if fatal {
+                        return None;
+                    }
+                    text.push(char::REPLACEMENT_CHARACTER);
+                }
+                Some(text)
+            }
+        }
+    }
+}
+
+/// The `TextDecoder` class
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "encoding")))]
+#[derive(Debug, Clone, Copy)]
+pub struct TextDecoder {
+    encoding: TextEncoding,
+    fatal: bool,
+    ignore_bom: bool,
+}
+
+class_def! {
+    TextDecoder (proto) {
+        proto.prop("encoding", Accessor::from(Method(|this: &TextDecoder| this.encoding.as_str())))?;
+        proto.prop("fatal", Accessor::from(Method(|this: &TextDecoder| this.fatal)))?;
+        proto.prop("ignoreBOM", Accessor::from(Method(|this: &TextDecoder| this.ignore_bom)))?;
+        proto.set("decode", Func::from(Method(TextDecoder::decode)))?;
+    }
+}
+
+impl TextDecoder {
+    /// Create decoder for given encoding
+    pub fn new_with(encoding: TextEncoding, fatal: bool, ignore_bom: bool) -> Self {
+        Self {
+            encoding,
+            fatal,
+            ignore_bom,
+        }
+    }
+
+    fn new<'js>(label: Opt<Coerced<StdString>>, options: Opt<Object<'js>>) -> Result<Self> {
+        let encoding = match label.0 {
+            Some(label) => TextEncoding::from_label(&label).ok_or_else(|| {
+                Error::new_from_js_message(
+                    "string",
+                    "TextDecoder",
+                    format!("The \"{}\" encoding is not supported", label.0),
+                )
+            })?,
+            None => TextEncoding::Utf8,
+        };
+        let (fatal, ignore_bom) = match options.0 {
+            Some(options) => (
+                options.get::<_, Coerced<bool>>("fatal")?.0,
+                options.get::<_, Coerced<bool>>("ignoreBOM")?.0,
+            ),
+            None => (false, false),
+        };
+        Ok(Self::new_with(encoding, fatal, ignore_bom))
+    }
+
+    fn decode<'js>(&self, input: Opt<Value<'js>>) -> Result<StdString> {
+        let bytes = match input.0 {
+            Some(input) => ArrayBuffer::copy_bytes(&input).ok_or_else(|| {
+                Error::new_from_js_message(
+                    input.type_of().as_str(),
+                    "bytes",
+                    "The input should be an ArrayBuffer or an ArrayBufferView",
+                )
+            })?,
+            None => Vec::new(),
+        };
+        self.encoding
+            .decode(&bytes, self.fatal, self.ignore_bom)
+            .ok_or_else(|| {
+                Error::new_from_js_message(
+                    "bytes",
+                    "string",
+                    format!(
+                        "The encoded data was not valid for encoding {}",
+                        self.encoding.as_str()
+                    ),
+                )
+            })
+    }
+}
+
+fn atob(data: Coerced<StdString>) -> Result<StdString> {
+    let error = || {
+        Error::new_from_js_message(
+            "string",
+            "bytes",
+            "The string to be decoded is not correctly encoded",
+        )
+    };
+
+    // forgiving base64 decode
+    let mut data = data
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\x0c' | '\r' | ' '))
+        .collect::<Vec<_>>();
+    if data.len() % 4 == 0 {
+        for _ in 0..2 {
+            if data.last() == Some(&'=') {
+                data.pop();
+            }
+        }
+    }
+    if data.len() % 4 == 1 {
+        return Err(error());
+    }
+
+    let mut output = StdString::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data {
+        let index = BASE64_ALPHABET
+            .iter()
+            .position(|b| *b as char == c)
+            .ok_or_else(error)?;
+        buffer = (buffer << 6) | index as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push(((buffer >> bits) & 0xff) as u8 as char);
+        }
+    }
+    Ok(output)
+}
+
+fn btoa(data: Coerced<StdString>) -> Result<StdString> {
+    let bytes = data
+        .chars()
+        .map(|c| {
+            if (c as u32) < 0x100 {
+                Ok(c as u8)
+            } else {
+                Err(Error::new_from_js_message(
+                    "string",
+                    "bytes",
+                    "The string to be encoded contains characters outside of the Latin1 range",
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut output = StdString::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |buffer, (i, b)| buffer | (*b as u32) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_ALPHABET[(buffer >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{encoding::*, *};
+
+    #[test]
+    fn encode() {
+        test_with(|ctx| {
+            Encoding.init(ctx).unwrap();
+            let bytes: TypedArray<u8> = ctx.eval("new TextEncoder().encode('a€😀')").unwrap();
+            let bytes: &[u8] = bytes.as_ref();
+            assert_eq!(bytes, &[0x61, 0xe2, 0x82, 0xac, 0xf0, 0x9f, 0x98, 0x80]);
+            let result: Vec<usize> = ctx
+                .eval(
+                    r#"
+                    const dest = new Uint8Array(5);
+                    const { read, written } = new TextEncoder().encodeInto('a€😀', dest);
+                    [read, written]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(result, [2, 4]);
+        });
+    }
+
+    #[test]
+    fn decode() {
+        test_with(|ctx| {
+            Encoding.init(ctx).unwrap();
+            let text: StdString = ctx
+                .eval("new TextDecoder().decode(Uint8Array.of(0xef, 0xbb, 0xbf, 0x61, 0xff))")
+                .unwrap();
+            assert_eq!(text, "a\u{fffd}");
+            let text: StdString = ctx
+                .eval("new TextDecoder('utf-8', { ignoreBOM: true }).decode(Uint8Array.of(0xef, 0xbb, 0xbf, 0x61).buffer)")
+                .unwrap();
+            assert_eq!(text, "\u{feff}a");
+            let text: StdString = ctx
+                .eval("new TextDecoder('UTF-16LE').decode(new DataView(Uint8Array.of(0, 0xff, 0xfe, 0x61, 0, 0x3d, 0xd8, 0, 0xde).buffer, 1))")
+                .unwrap();
+            assert_eq!(text, "a😀");
+            let encoding: StdString = ctx.eval("new TextDecoder('utf-16').encoding").unwrap();
+            assert_eq!(encoding, "utf-16le");
+            assert!(ctx
+                .eval::<(), _>(
+                    "new TextDecoder('utf-8', { fatal: true }).decode(Uint8Array.of(0xff))"
+                )
+                .is_err());
+            assert!(ctx.eval::<(), _>("new TextDecoder('latin1')").is_err());
+        });
+    }
+
+    #[test]
+    fn base64() {
+        test_with(|ctx| {
+            Encoding.init(ctx).unwrap();
+            let result: Vec<StdString> = ctx
+                .eval("[btoa(''), btoa('f'), btoa('fo'), btoa('foo'), btoa('\\xff\\xfe'), atob(' Zm9v YmE= '), atob('Zg')]")
+                .unwrap();
+            assert_eq!(result, ["", "Zg==", "Zm8=", "Zm9v", "//4=", "fooba", "f"]);
+            assert!(ctx.eval::<(), _>("btoa('€')").is_err());
+            assert!(ctx.eval::<(), _>("atob('Zm9vY')").is_err());
+            assert!(ctx.eval::<(), _>("atob('Zm$v')").is_err());
+        });
+    }
+}
@@ -0,0 +1,1338 @@
+//! The fetch built-in
+//!
+//! The [`Fetch`] installs the `fetch` function with `Request`, `Response` and `Headers` classes.
+//! The requests is served by the host using the user-defined [`FetchHandler`],
+//! so the scripts can be connected to any HTTP client or to the in-process stand-in without network at all.
+//!
+//! The bodies of requests and responses is represented by [`Body`] which can be streamed by chunks.
+//! The `body` property gives `ReadableStream`-like object which can be read using `getReader().read()` or `for await` loop.
+//!
+//! The responses is resolved asynchronously so the async executor should be started (see [`Runtime::run_executor`](crate::Runtime::run_executor)).
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, fetch::{Fetch, FetchRequest, FetchResponse}};
+//! # use futures_lite::future::{block_on, or};
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//!
+//! let fetch = Fetch::new(|request: FetchRequest| async move {
+//!     Ok(FetchResponse::new(200, format!("{} {}", request.method, request.url)))
+//! });
+//!
+//! ctx.with(|ctx| {
+//!     fetch.init(ctx).unwrap();
+//!     ctx.eval::<(), _>(r#"
+//!         fetch("http://local/hello", { method: "post" })
+//!             .then(response => response.text())
+//!             .then(text => { globalThis.text = text; });
+//!     "#).unwrap();
+//! });
+//!
+//! block_on(or(rt.run_executor(), rt.idle()));
+//!
+//! let text: String = ctx.with(|ctx| ctx.eval("text").unwrap());
+//! assert_eq!(text, "POST http://local/hello");
+//! ```
+
+use crate::{
+    Accessor, Array, ArrayBuffer, Class, ClassDef, ClassId, Coerced, Ctx, Error, FromJs, Func,
+    Function, IntoJs, Method, Mut, Object, Opt, ParallelSend, Persistent, Promised, Ref,
+    RefsMarker, Result, StdString, This, Type, TypedArray, Value,
+};
+use futures_lite::{Stream, StreamExt};
+use std::{future::Future, pin::Pin};
+
+/// The stream of body chunks
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub trait BodyStream: Stream<Item = Result<Vec<u8>>> + ParallelSend + 'static {}
+
+impl<T> BodyStream for T where T: Stream<Item = Result<Vec<u8>>> + ParallelSend + 'static {}
+
+/// The body of request or response
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+#[derive(Default)]
+pub enum Body {
+    /// No body
+    #[default]
+    Empty,
+    /// The body which is available at once
+    Bytes(Vec<u8>),
+    /// The body which is received by chunks
+    Stream(Pin<Box<dyn BodyStream>>),
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        Self::Bytes(bytes.into())
+    }
+}
+
+impl From<StdString> for Body {
+    fn from(text: StdString) -> Self {
+        Self::Bytes(text.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Self::Bytes(text.as_bytes().into())
+    }
+}
+
+impl Body {
+    /// Create the body from stream of chunks
+    pub fn stream<S: BodyStream>(stream: S) -> Self {
+        Self::Stream(Box::pin(stream))
+    }
+
+    /// Check that the body is empty
+    ///
+    /// The streaming body is never considered empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Empty => true,
+            Self::Bytes(bytes) => bytes.is_empty(),
+            Self::Stream(_) => false,
+        }
+    }
+
+    /// Receive the next chunk
+    pub async fn chunk(&mut self) -> Option<Result<Vec<u8>>> {
+        match self {
+            Self::Empty => None,
+            Self::Bytes(bytes) => {
+                let bytes = std::mem::take(bytes);
+                *self = Self::Empty;
+                if bytes.is_empty() {
+                    None
+                } else {
+                    Some(Ok(bytes))
+                }
+            }
+            Self::Stream(stream) => stream.next().await,
+        }
+    }
+
+    /// Receive the whole body
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        if let Self::Bytes(bytes) = self {
+            return Ok(bytes);
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = self.chunk().await {
+            bytes.extend(chunk?);
+        }
+        Ok(bytes)
+    }
+}
+
+/// The request which is passed to [`FetchHandler`]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct FetchRequest {
+    /// The request method like `GET` or `POST`
+    pub method: StdString,
+    /// The requested URL as it was given to `fetch`
+    pub url: StdString,
+    /// The request headers with lowercased names
+    pub headers: Vec<(StdString, StdString)>,
+    /// The request body
+    pub body: Body,
+}
+
+impl FetchRequest {
+    /// Get the first value of header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// The response which is produced by [`FetchHandler`]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct FetchResponse {
+    /// The status code
+    pub status: u16,
+    /// The status message
+    pub status_text: StdString,
+    /// The response headers
+    pub headers: Vec<(StdString, StdString)>,
+    /// The response body
+    pub body: Body,
+}
+
+impl FetchResponse {
+    /// Create response with status and body
+    pub fn new<B: Into<Body>>(status: u16, body: B) -> Self {
+        Self {
+            status,
+            status_text: StdString::new(),
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Add header to response
+    pub fn add_header<N: Into<StdString>, V: Into<StdString>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> &mut Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add header to response
+    #[must_use]
+    pub fn with_header<N: Into<StdString>, V: Into<StdString>>(
+        mut self,
+        name: N,
+        value: V,
+    ) -> Self {
+        self.add_header(name, value);
+        self
+    }
+
+    /// Set status message of response
+    #[must_use]
+    pub fn with_status_text<T: Into<StdString>>(mut self, text: T) -> Self {
+        self.status_text = text.into();
+        self
+    }
+
+    /// Get the first value of header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// The handler of requests
+///
+/// This trait is implemented for functions which takes [`FetchRequest`] and returns future of [`FetchResponse`].
+/// The failed requests is rejected with `TypeError` like network errors in browsers.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub trait FetchHandler: Clone + ParallelSend + 'static {
+    /// The future of response
+    type Future: Future<Output = Result<FetchResponse>> + ParallelSend + 'static;
+
+    /// Handle the request
+    fn fetch(&self, request: FetchRequest) -> Self::Future;
+}
+
+impl<F, R> FetchHandler for F
+where
+    F: Fn(FetchRequest) -> R + Clone + ParallelSend + 'static,
+    R: Future<Output = Result<FetchResponse>> + ParallelSend + 'static,
+{
+    type Future = R;
+
+    fn fetch(&self, request: FetchRequest) -> Self::Future {
+        self(request)
+    }
+}
+
+/// The fetch built-in
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+#[derive(Clone)]
+pub struct Fetch<H> {
+    handler: H,
+}
+
+impl<H: FetchHandler> Fetch<H> {
+    /// Create fetch built-in which uses the given handler to serve requests
+    pub fn new(handler: H) -> Self {
+        Self { handler }
+    }
+
+    /// Install `fetch`, `Request`, `Response`, `Headers` and `ReadableStream` into the global object of context
+    pub fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        let globals = ctx.globals();
+
+        Class::<Headers>::register(ctx)?;
+        globals.set(
+            "Headers",
+            Func::new("Headers", Class::<Headers>::constructor(Headers::construct)),
+        )?;
+
+        Class::<ReadableStream>::register(ctx)?;
+        globals.set(
+            "ReadableStream",
+            Func::new(
+                "ReadableStream",
+                Class::<ReadableStream>::constructor(|| -> Result<ReadableStream> {
+                    Err(Error::new_from_js_message(
+                        "arguments",
+                        "ReadableStream",
+                        "The ReadableStream cannot be constructed by scripts",
+                    ))
+                }),
+            ),
+        )?;
+
+        Class::<Request>::register(ctx)?;
+        globals.set(
+            "Request",
+            Func::new("Request", Class::<Request>::constructor(Request::construct)),
+        )?;
+
+        Class::<Response>::register(ctx)?;
+        globals.set(
+            "Response",
+            Func::new(
+                "Response",
+                Class::<Response>::constructor(Response::construct),
+            ),
+        )?;
+
+        let handler = self.handler.clone();
+        let fetch = Function::new(
+            ctx,
+            fetch_fn(move |ctx, input, init| {
+                let request = Request::construct(ctx, input, init)?;
+                let url = request.url.clone();
+                let future = handler.fetch(request.into_fetch_request());
+                Ok(Promised(async move {
+                    let response = future.await.map_err(|error| {
+                        Error::new_from_js_message("request", "response", error.to_string())
+                    })?;
+                    Ok::<_, Error>(Response::from_fetch_response(url, response))
+                }))
+            }),
+        )?;
+        fetch.set_name("fetch")?;
+        globals.set("fetch", fetch)?;
+
+        Ok(())
+    }
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn fetch_fn<F, R>(func: F) -> F
+where
+    F: for<'js> Fn(Ctx<'js>, Value<'js>, Opt<Object<'js>>) -> Result<Promised<R>>,
+{
+    func
+}
+
+fn find_header<'a>(headers: &'a [(StdString, StdString)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+type HeaderList = Vec<(StdString, StdString)>;
+
+/// The `Headers` class
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct Headers {
+    list: Ref<Mut<HeaderList>>,
+}
+
+impl ClassDef for Headers {
+    const CLASS_NAME: &'static str = "Headers";
+
+    fn class_id() -> &'static ClassId {
+        static CLASS_ID: ClassId = ClassId::new();
+        &CLASS_ID
+    }
+
+    const HAS_PROTO: bool = true;
+    fn init_proto<'js>(ctx: Ctx<'js>, proto: &Object<'js>) -> Result<()> {
+        proto.set("append", Func::from(Method(Headers::append)))?;
+        proto.set("delete", Func::from(Method(Headers::delete)))?;
+        proto.set("get", Func::from(Method(Headers::get)))?;
+        proto.set("has", Func::from(Method(Headers::has)))?;
+        proto.set("set", Func::from(Method(Headers::set)))?;
+        proto.set("forEach", Func::from(Headers::for_each))?;
+        proto.set("keys", Func::from(Method(Headers::keys)))?;
+        proto.set("values", Func::from(Method(Headers::values)))?;
+        let entries = Function::new(ctx, Method(Headers::entries))?;
+        entries.set_name("entries")?;
+        proto.set("entries", entries.clone())?;
+        proto.set(symbol(ctx, "iterator")?, entries)?;
+        Ok(())
+    }
+}
+
+impl<'js> IntoJs<'js> for Headers {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.into_js_obj(ctx)
+    }
+}
+
+impl<'js> FromJs<'js> for &'js Headers {
+    fn from_js(ctx: Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        Headers::from_js_ref(ctx, value)
+    }
+}
+
+impl Headers {
+    /// Create headers from name-value pairs
+    pub fn new(list: HeaderList) -> Self {
+        Self::shared(Ref::new(Mut::new(
+            list.into_iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), normalize_value(&value)))
+                .collect(),
+        )))
+    }
+
+    fn shared(list: Ref<Mut<HeaderList>>) -> Self {
+        Self { list }
+    }
+
+    /// Get the name-value pairs
+    pub fn pairs(&self) -> HeaderList {
+        self.list.lock().clone()
+    }
+
+    fn construct<'js>(ctx: Ctx<'js>, init: Opt<Value<'js>>) -> Result<Self> {
+        let list = match init.0 {
+            Some(init) => header_list(ctx, init)?,
+            None => Vec::new(),
+        };
+        Ok(Self::shared(Ref::new(Mut::new(list))))
+    }
+
+    fn append(&self, name: Coerced<StdString>, value: Coerced<StdString>) -> Result<()> {
+        let name = header_name(&name)?;
+        self.list.lock().push((name, normalize_value(&value)));
+        Ok(())
+    }
+
+    fn delete(&self, name: Coerced<StdString>) -> Result<()> {
+        let name = header_name(&name)?;
+        self.list.lock().retain(|(key, _)| *key != name);
+        Ok(())
+    }
+
+    fn get<'js>(&self, ctx: Ctx<'js>, name: Coerced<StdString>) -> Result<Value<'js>> {
+        let name = header_name(&name)?;
+        let values = self
+            .list
+            .lock()
+            .iter()
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            Ok(Value::new_null(ctx))
+        } else {
+            values.join(", ").into_js(ctx)
+        }
+    }
+
+    fn has(&self, name: Coerced<StdString>) -> Result<bool> {
+        let name = header_name(&name)?;
+        Ok(self.list.lock().iter().any(|(key, _)| *key == name))
+    }
+
+    fn set(&self, name: Coerced<StdString>, value: Coerced<StdString>) -> Result<()> {
+        let name = header_name(&name)?;
+        let mut value = Some(normalize_value(&value));
+        let mut list = self.list.lock();
+        list.retain_mut(|(key, val)| {
+            if *key != name {
+                return true;
+            }
+            // replace the first occurrence and remove others
+            match value.take() {
+                Some(value) => {
+                    *val = value;
+                    true
+                }
+                None => false,
+            }
+        });
+        if let Some(value) = value {
+            list.push((name, value));
+        }
+        Ok(())
+    }
+
+    /// Get the sorted pairs with combined values like the iteration does
+    fn sorted(&self) -> HeaderList {
+        let mut sorted: HeaderList = Vec::new();
+        let mut list = self.pairs();
+        list.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, value) in list {
+            match sorted.last_mut() {
+                Some((last, values)) if *last == name => {
+                    values.push_str(", ");
+                    values.push_str(&value);
+                }
+                _ => sorted.push((name, value)),
+            }
+        }
+        sorted
+    }
+
+    fn for_each<'js>(
+        ctx: Ctx<'js>,
+        this: This<Object<'js>>,
+        callback: Function<'js>,
+        this_arg: Opt<Value<'js>>,
+    ) -> Result<()> {
+        let headers = Class::<Headers>::try_ref(ctx, &this.0)?;
+        let this_arg = match this_arg.0 {
+            Some(this_arg) => this_arg,
+            None => Value::new_undefined(ctx),
+        };
+        for (name, value) in headers.sorted() {
+            callback.call::<_, ()>((This(this_arg.clone()), value, name, this.0.clone()))?;
+        }
+        Ok(())
+    }
+
+    fn keys<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        iterate(ctx, self.sorted().into_iter().map(|(name, _)| name))
+    }
+
+    fn values<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        iterate(ctx, self.sorted().into_iter().map(|(_, value)| value))
+    }
+
+    fn entries<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        iterate(
+            ctx,
+            self.sorted()
+                .into_iter()
+                .map(|(name, value)| vec![name, value]),
+        )
+    }
+}
+
+fn header_name(name: &str) -> Result<StdString> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c));
+    if valid {
+        Ok(name.to_ascii_lowercase())
+    } else {
+        Err(Error::new_from_js_message(
+            "string",
+            "header name",
+            format!("Invalid header name: \"{}\"", name),
+        ))
+    }
+}
+
+fn normalize_value(value: &str) -> StdString {
+    value
+        .trim_matches(|c| matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .into()
+}
+
+/// Get the header list from `Headers`, sequence of pairs or record
+fn header_list<'js>(ctx: Ctx<'js>, init: Value<'js>) -> Result<HeaderList> {
+    if matches!(init.type_of(), Type::Undefined) {
+        return Ok(Vec::new());
+    }
+    let object = init.as_object().ok_or_else(|| {
+        Error::new_from_js_message(
+            init.type_of().as_str(),
+            "Headers",
+            "The headers should be an object",
+        )
+    })?;
+    if let Ok(headers) = Class::<Headers>::try_ref(ctx, object) {
+        return Ok(headers.pairs());
+    }
+    let mut list = Vec::new();
+    if object
+        .get::<_, Value>(symbol(ctx, "iterator")?)?
+        .as_function()
+        .is_some()
+    {
+        let array_from: Function = ctx.globals().get::<_, Object>("Array")?.get("from")?;
+        let pairs: Array = array_from.call((init.clone(),))?;
+        for pair in pairs.iter::<Value>() {
+            let pair: Array = array_from.call((pair?,))?;
+            if pair.len() != 2 {
+                return Err(Error::new_from_js_message(
+                    "array",
+                    "header",
+                    "Each header must be an iterable [name, value] tuple",
+                ));
+            }
+            let name = pair.get::<Coerced<StdString>>(0)?;
+            let value = pair.get::<Coerced<StdString>>(1)?;
+            list.push((header_name(&name)?, normalize_value(&value)));
+        }
+    } else {
+        for prop in object.props::<StdString, Coerced<StdString>>() {
+            let (name, value) = prop?;
+            list.push((header_name(&name)?, normalize_value(&value)));
+        }
+    }
+    Ok(list)
+}
+
+struct BodyState {
+    body: Option<Body>,
+    used: bool,
+    reading: bool,
+    stream: Option<Persistent<Object<'static>>>,
+}
+
+/// The body which is shared between request or response and its stream
+#[derive(Clone)]
+struct BodyCell(Ref<Mut<BodyState>>);
+
+impl BodyCell {
+    fn new(body: Body) -> Self {
+        Self(Ref::new(Mut::new(BodyState {
+            body: Some(body),
+            used: false,
+            reading: false,
+            stream: None,
+        })))
+    }
+
+    fn is_used(&self) -> bool {
+        self.0.lock().used
+    }
+
+    /// Take the whole body to consume it
+    fn take(&self) -> Result<Body> {
+        let mut state = self.0.lock();
+        if state.used {
+            return Err(Error::new_from_js_message(
+                "body",
+                "bytes",
+                "The body has already been used",
+            ));
+        }
+        state.used = true;
+        Ok(state.body.take().unwrap_or_default())
+    }
+
+    /// Receive the next chunk of body
+    ///
+    /// The concurrent reads is rejected because the body is taken while reading.
+    async fn chunk(self) -> Result<Option<Vec<u8>>> {
+        let body = {
+            let mut state = self.0.lock();
+            if state.reading {
+                return Err(Error::new_from_js_message(
+                    "stream",
+                    "chunk",
+                    "The reader is busy with the previous read",
+                ));
+            }
+            state.used = true;
+            state.reading = state.body.is_some();
+            state.body.take()
+        };
+        let mut body = match body {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        let chunk = body.chunk().await.transpose();
+        let mut state = self.0.lock();
+        state.reading = false;
+        state.body = Some(body);
+        chunk
+    }
+
+    fn mark_refs(&self, marker: &RefsMarker) {
+        use crate::HasRefs;
+        self.0.lock().stream.mark_refs(marker);
+    }
+
+    /// Get the `ReadableStream` object or null when the body is empty
+    fn stream<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        // The lock shouldn't be held while allocating because the GC marks the object
+        let (cached, empty) = {
+            let state = self.0.lock();
+            let empty = !state.used && state.body.as_ref().map(Body::is_empty).unwrap_or(true);
+            (state.stream.clone(), empty)
+        };
+        if let Some(stream) = cached {
+            return stream.restore(ctx).map(|stream| stream.into_value());
+        }
+        if empty {
+            return Ok(Value::new_null(ctx));
+        }
+        let stream = Class::instance(ctx, ReadableStream { body: self.clone() })?.into_object();
+        self.0.lock().stream = Some(Persistent::save(ctx, stream.clone()));
+        Ok(stream.into_value())
+    }
+}
+
+/// The common body methods of requests and responses
+trait HasBody: ClassDef + Sized + 'static {
+    fn body(&self) -> &BodyCell;
+
+    fn init_body_proto<'js>(proto: &Object<'js>) -> Result<()>
+    where
+        for<'a> &'a Self: FromJs<'a>,
+    {
+        proto.prop(
+            "body",
+            Accessor::from(Method(body_stream::<Self>)).enumerable(),
+        )?;
+        proto.prop(
+            "bodyUsed",
+            Accessor::from(Method(|this: &Self| this.body().is_used())).enumerable(),
+        )?;
+        proto.set("text", Func::from(Method(text::<Self>)))?;
+        proto.set("json", Func::from(Method(json::<Self>)))?;
+        proto.set("arrayBuffer", Func::from(Method(array_buffer::<Self>)))?;
+        proto.set("bytes", Func::from(Method(bytes::<Self>)))?;
+        Ok(())
+    }
+}
+
+fn body_stream<'js, T: HasBody>(this: &T, ctx: Ctx<'js>) -> Result<Value<'js>> {
+    this.body().stream(ctx)
+}
+
+fn text<T: HasBody>(this: &T) -> Promised<impl Future<Output = Result<StdString>>> {
+    let body = this.body().take();
+    Promised(async move {
+        let bytes = body?.bytes().await?;
+        Ok(StdString::from_utf8_lossy(&bytes).into_owned())
+    })
+}
+
+fn json<T: HasBody>(this: &T) -> Promised<impl Future<Output = Result<Json>>> {
+    let body = this.body().take();
+    Promised(async move {
+        let bytes = body?.bytes().await?;
+        Ok(Json(StdString::from_utf8_lossy(&bytes).into_owned()))
+    })
+}
+
+fn array_buffer<T: HasBody>(this: &T) -> Promised<impl Future<Output = Result<Bytes>>> {
+    let body = this.body().take();
+    Promised(async move { Ok(Bytes(body?.bytes().await?, false)) })
+}
+
+fn bytes<T: HasBody>(this: &T) -> Promised<impl Future<Output = Result<Bytes>>> {
+    let body = this.body().take();
+    Promised(async move { Ok(Bytes(body?.bytes().await?, true)) })
+}
+
+/// The JSON text which is parsed when converting to JS
+struct Json(StdString);
+
+impl<'js> IntoJs<'js> for Json {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let parse: Function = ctx.globals().get::<_, Object>("JSON")?.get("parse")?;
+        parse.call((self.0,))
+    }
+}
+
+/// The bytes which is converted to `ArrayBuffer` or `Uint8Array`
+struct Bytes(Vec<u8>, bool);
+
+impl<'js> IntoJs<'js> for Bytes {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        if self.1 {
+            TypedArray::new(ctx, self.0).map(|array| array.into_value())
+        } else {
+            ArrayBuffer::new(ctx, self.0).map(|buffer| buffer.into_value())
+        }
+    }
+}
+
+/// The result of reading stream
+struct ReadResult(Option<Vec<u8>>);
+
+impl<'js> IntoJs<'js> for ReadResult {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let result = Object::new(ctx)?;
+        result.set("done", self.0.is_none())?;
+        if let Some(chunk) = self.0 {
+            result.set("value", TypedArray::new(ctx, chunk)?)?;
+        }
+        Ok(result.into_value())
+    }
+}
+
+/// Get the body from string, `ArrayBuffer`, its views or `Blob`-like value
+fn body_init<'js>(value: Value<'js>) -> Result<(Body, Option<&'static str>)> {
+    Ok(match value.type_of() {
+        Type::Undefined | Type::Null => (Body::Empty, None),
+        Type::String => (
+            Body::from(StdString::from_js(value.ctx, value)?),
+            Some("text/plain;charset=UTF-8"),
+        ),
+        _ => match ArrayBuffer::copy_bytes(&value) {
+            Some(bytes) => (Body::from(bytes), None),
+            None => (
+                Body::from(Coerced::<StdString>::from_js(value.ctx, value)?.0),
+                Some("text/plain;charset=UTF-8"),
+            ),
+        },
+    })
+}
+
+/// The `Request` class
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct Request {
+    method: StdString,
+    url: StdString,
+    headers: Ref<Mut<HeaderList>>,
+    headers_object: Mut<Option<Persistent<Object<'static>>>>,
+    body: BodyCell,
+}
+
+impl HasBody for Request {
+    fn body(&self) -> &BodyCell {
+        &self.body
+    }
+}
+
+impl ClassDef for Request {
+    const CLASS_NAME: &'static str = "Request";
+
+    fn class_id() -> &'static ClassId {
+        static CLASS_ID: ClassId = ClassId::new();
+        &CLASS_ID
+    }
+
+    const HAS_PROTO: bool = true;
+    fn init_proto<'js>(_ctx: Ctx<'js>, proto: &Object<'js>) -> Result<()> {
+        proto.prop(
+            "method",
+            Accessor::from(Method(|this: &Request| this.method.clone())).enumerable(),
+        )?;
+        proto.prop(
+            "url",
+            Accessor::from(Method(|this: &Request| this.url.clone())).enumerable(),
+        )?;
+        proto.prop(
+            "headers",
+            Accessor::from(Method(Request::headers)).enumerable(),
+        )?;
+        Self::init_body_proto(proto)
+    }
+
+    const HAS_REFS: bool = true;
+    fn mark_refs(&self, marker: &RefsMarker) {
+        use crate::HasRefs;
+        self.headers_object.lock().mark_refs(marker);
+        self.body.mark_refs(marker);
+    }
+}
+
+impl<'js> IntoJs<'js> for Request {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.into_js_obj(ctx)
+    }
+}
+
+impl<'js> FromJs<'js> for &'js Request {
+    fn from_js(ctx: Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        Request::from_js_ref(ctx, value)
+    }
+}
+
+impl Request {
+    fn construct<'js>(ctx: Ctx<'js>, input: Value<'js>, init: Opt<Object<'js>>) -> Result<Self> {
+        let source = input
+            .as_object()
+            .and_then(|input| Class::<Request>::try_ref(ctx, input).ok());
+        let (mut method, url, mut headers, mut body) = match source {
+            Some(source) => (
+                source.method.clone(),
+                source.url.clone(),
+                source.headers.lock().clone(),
+                source.body.clone(),
+            ),
+            None => (
+                "GET".into(),
+                Coerced::<StdString>::from_js(ctx, input)?.0,
+                Vec::new(),
+                BodyCell::new(Body::Empty),
+            ),
+        };
+
+        if let Some(init) = init.0 {
+            if let Some(value) = init.get::<_, Option<Coerced<StdString>>>("method")? {
+                method = value.0;
+                let upper = method.to_ascii_uppercase();
+                if ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"].contains(&upper.as_str()) {
+                    method = upper;
+                }
+            }
+            let value: Value = init.get("headers")?;
+            if value.type_of() != Type::Undefined {
+                headers = header_list(ctx, value)?;
+            }
+            let value: Value = init.get("body")?;
+            if value.type_of() != Type::Undefined {
+                let (value, content_type) = body_init(value)?;
+                if let Some(content_type) = content_type {
+                    if find_header(&headers, "content-type").is_none() {
+                        headers.push(("content-type".into(), content_type.into()));
+                    }
+                }
+                body = BodyCell::new(value);
+            }
+        }
+
+        if (method == "GET" || method == "HEAD")
+            && body
+                .0
+                .lock()
+                .body
+                .as_ref()
+                .map(|body| !body.is_empty())
+                .unwrap_or(false)
+        {
+            return Err(Error::new_from_js_message(
+                "body",
+                "Request",
+                "Request with GET/HEAD method cannot have body",
+            ));
+        }
+
+        Ok(Self {
+            method,
+            url,
+            headers: Ref::new(Mut::new(headers)),
+            headers_object: Mut::new(None),
+            body,
+        })
+    }
+
+    fn headers<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        headers_object(ctx, &self.headers, &self.headers_object)
+    }
+
+    fn into_fetch_request(self) -> FetchRequest {
+        FetchRequest {
+            method: self.method,
+            url: self.url,
+            headers: self.headers.lock().clone(),
+            body: self.body.take().unwrap_or_default(),
+        }
+    }
+}
+
+/// The `Response` class
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct Response {
+    status: u16,
+    status_text: StdString,
+    url: StdString,
+    headers: Ref<Mut<HeaderList>>,
+    headers_object: Mut<Option<Persistent<Object<'static>>>>,
+    body: BodyCell,
+}
+
+impl HasBody for Response {
+    fn body(&self) -> &BodyCell {
+        &self.body
+    }
+}
+
+impl ClassDef for Response {
+    const CLASS_NAME: &'static str = "Response";
+
+    fn class_id() -> &'static ClassId {
+        static CLASS_ID: ClassId = ClassId::new();
+        &CLASS_ID
+    }
+
+    const HAS_PROTO: bool = true;
+    fn init_proto<'js>(_ctx: Ctx<'js>, proto: &Object<'js>) -> Result<()> {
+        proto.prop(
+            "status",
+            Accessor::from(Method(|this: &Response| this.status)).enumerable(),
+        )?;
+        proto.prop(
+            "statusText",
+            Accessor::from(Method(|this: &Response| this.status_text.clone())).enumerable(),
+        )?;
+        proto.prop(
+            "ok",
+            Accessor::from(Method(|this: &Response| (200..300).contains(&this.status)))
+                .enumerable(),
+        )?;
+        proto.prop(
+            "url",
+            Accessor::from(Method(|this: &Response| this.url.clone())).enumerable(),
+        )?;
+        proto.prop(
+            "headers",
+            Accessor::from(Method(Response::headers)).enumerable(),
+        )?;
+        Self::init_body_proto(proto)
+    }
+
+    const HAS_STATIC: bool = true;
+    fn init_static<'js>(_ctx: Ctx<'js>, ctor: &Object<'js>) -> Result<()> {
+        ctor.set("json", Func::from(Response::json))?;
+        Ok(())
+    }
+
+    const HAS_REFS: bool = true;
+    fn mark_refs(&self, marker: &RefsMarker) {
+        use crate::HasRefs;
+        self.headers_object.lock().mark_refs(marker);
+        self.body.mark_refs(marker);
+    }
+}
+
+impl<'js> IntoJs<'js> for Response {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.into_js_obj(ctx)
+    }
+}
+
+impl<'js> FromJs<'js> for &'js Response {
+    fn from_js(ctx: Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        Response::from_js_ref(ctx, value)
+    }
+}
+
+impl Response {
+    fn headers<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        headers_object(ctx, &self.headers, &self.headers_object)
+    }
+
+    fn from_fetch_response(url: StdString, response: FetchResponse) -> Self {
+        Self {
+            status: response.status,
+            status_text: response.status_text,
+            url,
+            headers: Ref::new(Mut::new(
+                response
+                    .headers
+                    .into_iter()
+                    .map(|(name, value)| (name.to_ascii_lowercase(), normalize_value(&value)))
+                    .collect(),
+            )),
+            headers_object: Mut::new(None),
+            body: BodyCell::new(response.body),
+        }
+    }
+
+    fn construct<'js>(
+        ctx: Ctx<'js>,
+        body: Opt<Value<'js>>,
+        init: Opt<Object<'js>>,
+    ) -> Result<Self> {
+        let mut response = FetchResponse::new(200, Body::Empty);
+        let mut content_type = None;
+        if let Some(body) = body.0 {
+            let (body, body_type) = body_init(body)?;
+            response.body = body;
+            content_type = body_type;
+        }
+        if let Some(init) = init.0 {
+            if let Some(status) = init.get::<_, Option<f64>>("status")? {
+                if !(200.0..=599.0).contains(&status) || status.fract() != 0.0 {
+                    return Err(Error::new_from_js_message(
+                        "number",
+                        "Response",
+                        format!("The status {} is outside of the range [200, 599]", status),
+                    ));
+                }
+                response.status = status as u16;
+            }
+            if let Some(text) = init.get::<_, Option<Coerced<StdString>>>("statusText")? {
+                response.status_text = text.0;
+            }
+            response.headers = header_list(ctx, init.get("headers")?)?;
+        }
+        if let Some(content_type) = content_type {
+            if response.header("content-type").is_none() {
+                response.add_header("content-type", content_type);
+            }
+        }
+        Ok(Self::from_fetch_response(StdString::new(), response))
+    }
+
+    fn json<'js>(ctx: Ctx<'js>, data: Value<'js>, init: Opt<Object<'js>>) -> Result<Self> {
+        let stringify: Function = ctx.globals().get::<_, Object>("JSON")?.get("stringify")?;
+        let text: Option<StdString> = stringify.call((data,))?;
+        let text = text.ok_or_else(|| {
+            Error::new_from_js_message("value", "JSON", "The data is not JSON serializable")
+        })?;
+        let response = Self::construct(ctx, Opt(Some(text.into_js(ctx)?)), init)?;
+        {
+            let mut headers = response.headers.lock();
+            match headers.iter_mut().find(|(name, _)| name == "content-type") {
+                Some((_, value)) if value.starts_with("text/plain") => {
+                    *value = "application/json".into()
+                }
+                Some(_) => {}
+                None => headers.push(("content-type".into(), "application/json".into())),
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Get the `Headers` object which is bound to the header list
+fn headers_object<'js>(
+    ctx: Ctx<'js>,
+    list: &Ref<Mut<HeaderList>>,
+    cache: &Mut<Option<Persistent<Object<'static>>>>,
+) -> Result<Object<'js>> {
+    // The lock shouldn't be held while allocating because the GC marks the object
+    let cached = cache.lock().clone();
+    if let Some(headers) = cached {
+        return headers.restore(ctx);
+    }
+    let headers = Class::instance(ctx, Headers::shared(list.clone()))?.into_object();
+    *cache.lock() = Some(Persistent::save(ctx, headers.clone()));
+    Ok(headers)
+}
+
+/// The `ReadableStream`-like class of bodies
+///
+/// The stream is itself a reader and an async iterator over `Uint8Array` chunks.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub struct ReadableStream {
+    body: BodyCell,
+}
+
+impl ClassDef for ReadableStream {
+    const CLASS_NAME: &'static str = "ReadableStream";
+
+    fn class_id() -> &'static ClassId {
+        static CLASS_ID: ClassId = ClassId::new();
+        &CLASS_ID
+    }
+
+    const HAS_REFS: bool = true;
+    fn mark_refs(&self, marker: &RefsMarker) {
+        // The body holds the stream object itself
+        self.body.mark_refs(marker);
+    }
+
+    const HAS_PROTO: bool = true;
+    fn init_proto<'js>(ctx: Ctx<'js>, proto: &Object<'js>) -> Result<()> {
+        let read = Function::new(ctx, Method(ReadableStream::read))?;
+        read.set_name("read")?;
+        proto.set("read", read.clone())?;
+        proto.set("next", read)?;
+        proto.set("cancel", Func::from(Method(ReadableStream::cancel)))?;
+        proto.set("return", Func::from(Method(ReadableStream::cancel)))?;
+        proto.set("releaseLock", Func::from(Method(|_: &ReadableStream| {})))?;
+        let this = Function::new(ctx, ReadableStream::itself)?;
+        proto.set("getReader", this.clone())?;
+        proto.set(symbol(ctx, "asyncIterator")?, this)?;
+        Ok(())
+    }
+}
+
+impl<'js> IntoJs<'js> for ReadableStream {
+    fn into_js(self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        self.into_js_obj(ctx)
+    }
+}
+
+impl<'js> FromJs<'js> for &'js ReadableStream {
+    fn from_js(ctx: Ctx<'js>, value: Value<'js>) -> Result<Self> {
+        ReadableStream::from_js_ref(ctx, value)
+    }
+}
+
+impl ReadableStream {
+    fn read(&self) -> Promised<impl Future<Output = Result<ReadResult>>> {
+        let body = self.body.clone();
+        Promised(async move { body.chunk().await.map(ReadResult) })
+    }
+
+    fn itself<'js>(this: This<Value<'js>>) -> Value<'js> {
+        this.0
+    }
+
+    fn cancel(&self) -> Promised<impl Future<Output = ReadResult>> {
+        self.body.0.lock().body = None;
+        Promised(async { ReadResult(None) })
+    }
+}
+
+fn symbol<'js>(ctx: Ctx<'js>, name: &str) -> Result<Value<'js>> {
+    ctx.globals().get::<_, Object>("Symbol")?.get(name)
+}
+
+/// Create iterator over the snapshot of items
+fn iterate<'js, I>(ctx: Ctx<'js>, items: I) -> Result<Value<'js>>
+where
+    I: Iterator,
+    I::Item: IntoJs<'js>,
+{
+    let array = Array::new(ctx)?;
+    for (index, item) in items.enumerate() {
+        array.set(index, item)?;
+    }
+    let values: Function = array.as_object().get("values")?;
+    values.call((This(array),))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fetch::*, *};
+    use futures_lite::{
+        future::{self, block_on, or},
+        stream,
+    };
+
+    fn eval_async(handler: impl FetchHandler, source: &str) -> StdString {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        ctx.with(|ctx| {
+            Fetch::new(handler).init(ctx).unwrap();
+            let _: Value = ctx
+                .eval(format!(
+                    "(async () => {{ {} }})().then(result => {{ globalThis.result = result; }}, error => {{ globalThis.result = 'Error: ' + error.message; }});",
+                    source
+                ))
+                .unwrap();
+        });
+        block_on(or(rt.run_executor(), rt.idle()));
+        ctx.with(|ctx| ctx.eval("result").unwrap())
+    }
+
+    #[test]
+    fn headers() {
+        test_with(|ctx| {
+            Fetch::new(|_| async { Ok(FetchResponse::new(200, "")) })
+                .init(ctx)
+                .unwrap();
+            let result: Vec<StdString> = ctx
+                .eval(
+                    r#"
+                    const headers = new Headers({ "Content-Type": "text/plain", Accept: " a " });
+                    headers.append("accept", "b");
+                    headers.set("X-One", "1");
+                    const result = [headers.get("ACCEPT"), String(headers.get("x-none")), String(headers.has("x-one"))];
+                    headers.delete("content-type");
+                    result.push(JSON.stringify([...headers]));
+                    result.push(JSON.stringify([...new Headers([["b", "2"], ["a", "1"]]).keys()]));
+                    try { headers.set("bad name", ""); } catch (e) { result.push(e.constructor.name); }
+                    result
+                "#,
+                )
+                .unwrap();
+            assert_eq!(
+                result,
+                [
+                    "a, b",
+                    "null",
+                    "true",
+                    r#"[["accept","a, b"],["x-one","1"]]"#,
+                    r#"["a","b"]"#,
+                    "TypeError"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn request() {
+        let result = eval_async(
+            |request: FetchRequest| async move {
+                let content_type = request.header("content-type").unwrap_or("-").to_string();
+                let body = request.body.bytes().await?;
+                Ok(FetchResponse::new(
+                    201,
+                    format!(
+                        "{} {} {} {}",
+                        request.method,
+                        request.url,
+                        content_type,
+                        StdString::from_utf8_lossy(&body)
+                    ),
+                )
+                .with_status_text("Created")
+                .with_header("X-Reply", "yes"))
+            },
+            r#"
+            const response = await fetch(new Request("http://local/a", { method: "put", body: "data" }));
+            return [response.status, response.statusText, response.ok, response.url,
+                    response.headers.get("x-reply"), response.bodyUsed, await response.text(), response.bodyUsed].join();
+            "#,
+        );
+        assert_eq!(
+            result,
+            "201,Created,true,http://local/a,yes,false,PUT http://local/a text/plain;charset=UTF-8 data,true"
+        );
+    }
+
+    #[test]
+    fn response() {
+        let result = eval_async(
+            |_| async { Ok(FetchResponse::new(200, "")) },
+            r#"
+            const json = await Response.json({ a: [1, 2] }, { status: 202 }).json();
+            const bytes = await new Response(Uint8Array.of(1, 2, 3)).arrayBuffer();
+            const response = new Response("text");
+            await response.text();
+            let used;
+            try { await response.text(); } catch (e) { used = e.message; }
+            return JSON.stringify([json, bytes.byteLength, used.includes("already"), new Response().body]);
+            "#,
+        );
+        assert_eq!(result, r#"[{"a":[1,2]},3,true,null]"#);
+    }
+
+    #[test]
+    fn streaming() {
+        let result = eval_async(
+            |_| async {
+                Ok(FetchResponse::new(
+                    200,
+                    Body::stream(stream::iter(vec![Ok(b"ab".to_vec()), Ok(b"c".to_vec())])),
+                ))
+            },
+            r#"
+            const chunks = [];
+            for await (const chunk of (await fetch("http://local/")).body) {
+                chunks.push(Array.from(chunk).join());
+            }
+            const reader = (await fetch("http://local/")).body.getReader();
+            const first = await reader.read();
+            return chunks.join("|") + " " + first.value.length + " " + first.done;
+            "#,
+        );
+        assert_eq!(result, "97,98|99 2 false");
+    }
+
+    #[test]
+    fn concurrent_read() {
+        let result = eval_async(
+            |_| async {
+                let chunks = stream::unfold(0u8, |n| async move {
+                    future::yield_now().await;
+                    if n < 2 {
+                        Some((Ok(vec![n]), n + 1))
+                    } else {
+                        None
+                    }
+                });
+                Ok(FetchResponse::new(200, Body::stream(chunks)))
+            },
+            r#"
+            const reader = (await fetch("http://local/")).body.getReader();
+            const [first, second] = await Promise.allSettled([reader.read(), reader.read()]);
+            const third = await reader.read();
+            return [first.value.value[0], second.status, third.value[0], (await reader.read()).done].join();
+            "#,
+        );
+        assert_eq!(result, "0,rejected,1,true");
+    }
+
+    #[test]
+    fn failure() {
+        let result = eval_async(
+            |_| async { Err(Error::new_loading("http://local/")) },
+            r#"await fetch("http://local/"); return "unreachable";"#,
+        );
+        assert!(result.starts_with("Error: "));
+    }
+}
@@ -0,0 +1,526 @@
+//! The sandboxed filesystem module
+//!
+//! The [`Fs`] gives the scripts access to the files under the configured root directories only.
+//! Each root can be read-only or writable (see [`Access`]).
+//! The paths which is resolved outside of any root (i.e. using `..` or symbolic links) is rejected.
+//! The paths which goes through dangling symbolic links is rejected too because its targets may be created outside of roots.
+//! The relative paths is resolved against the first root.
+//!
+//! The module exports the Node-like functions `readFile`, `writeFile`, `readdir`, `stat`, `mkdir` and `remove`
+//! which returns promises, and the same functions with `Sync` suffix which returns the results directly.
+//! The promise-returning functions does the I/O before returning so the promises is already settled.
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, BuiltinResolver, SyntheticLoader, fs::{Fs, Access}};
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//!
+//! let dir = std::env::temp_dir().join("rquickjs_fs_doc");
+//! std::fs::create_dir_all(&dir).unwrap();
+//! let fs = Fs::default().with_root(&dir, Access::ReadWrite);
+//!
+//! rt.set_loader(
+//!     BuiltinResolver::default().with_module("fs"),
+//!     SyntheticLoader::default().with_module_fn("fs", move |ctx| fs.exports(ctx)),
+//! );
+//!
+//! ctx.with(|ctx| {
+//!     ctx.compile("main", r#"
+//!         import { writeFileSync, readFileSync } from "fs";
+//!         writeFileSync("hello.txt", "Hello!");
+//!         globalThis.text = readFileSync("hello.txt", "utf8");
+//!     "#).unwrap();
+//!     let text: String = ctx.eval("text").unwrap();
+//!     assert_eq!(text, "Hello!");
+//! });
+//! ```
+
+use crate::{
+    ArrayBuffer, Coerced, Ctx, Error, FromJs, Func, IntoJs, Object, Opt, Result, StdString, Type,
+    TypedArray, Value,
+};
+use std::{
+    env, fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The access mode of root directory
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fs")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Only reading of files and directories is allowed
+    ReadOnly,
+    /// Both reading and modification is allowed
+    ReadWrite,
+}
+
+#[derive(Debug, Clone)]
+struct Root {
+    path: PathBuf,
+    access: Access,
+}
+
+/// The sandboxed filesystem module
+///
+/// The exports is created by [`Fs::exports`] which can be used with [`SyntheticLoader::with_module_fn`](crate::SyntheticLoader::with_module_fn).
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fs")))]
+#[derive(Debug, Clone, Default)]
+pub struct Fs {
+    roots: Vec<Root>,
+}
+
+type OpFn =
+    for<'js> fn(&Fs, Ctx<'js>, StdString, Opt<Value<'js>>, Opt<Value<'js>>) -> Result<Value<'js>>;
+
+/// The operations exported as `name` returning promise and `nameSync`
+const OPS: &[(&str, &str, OpFn)] = &[
+    ("readFile", "readFileSync", Fs::read_file),
+    ("writeFile", "writeFileSync", Fs::write_file),
+    ("readdir", "readdirSync", Fs::read_dir),
+    ("stat", "statSync", Fs::stat),
+    ("mkdir", "mkdirSync", Fs::mkdir),
+    ("remove", "removeSync", Fs::remove),
+];
+
+impl Fs {
+    /// Add root directory
+    ///
+    /// The relative paths is resolved against the current directory.
+    /// When the roots is nested the access of the innermost one is applied.
+    pub fn add_root<P: Into<PathBuf>>(&mut self, path: P, access: Access) -> &mut Self {
+        let path = path.into();
+        let path = if path.is_absolute() {
+            path
+        } else {
+            env::current_dir()
+                .map(|dir| dir.join(&path))
+                .unwrap_or(path)
+        };
+        self.roots.push(Root {
+            path: normalize(&path),
+            access,
+        });
+        self
+    }
+
+    /// Add root directory
+    #[must_use]
+    pub fn with_root<P: Into<PathBuf>>(mut self, path: P, access: Access) -> Self {
+        self.add_root(path, access);
+        self
+    }
+
+    /// Create the exports object of module
+    ///
+    /// The `default` export contains all the functions too.
+    pub fn exports<'js>(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let exports = Object::new(ctx)?;
+        for &(name, sync_name, op) in OPS {
+            let fs = self.clone();
+            exports.set(
+                sync_name,
+                Func::new(
+                    sync_name,
+                    op_fn(move |ctx, path, arg, options| op(&fs, ctx, path.0, arg, options)),
+                ),
+            )?;
+            let fs = self.clone();
+            exports.set(
+                name,
+                Func::new(
+                    name,
+                    op_fn(move |ctx, path, arg, options| {
+                        promised(ctx, op(&fs, ctx, path.0, arg, options))
+                    }),
+                ),
+            )?;
+        }
+        exports.set("default", exports.clone())?;
+        Ok(exports)
+    }
+
+    /// Resolve the path and check the access to it
+    ///
+    /// The returned path has the symbolic links of existing part resolved.
+    pub fn resolve<P: AsRef<Path>>(&self, path: P, access: Access) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let path = if path.is_absolute() {
+            normalize(path)
+        } else {
+            let base = self.roots.first().ok_or_else(|| denied(path))?;
+            normalize(&base.path.join(path))
+        };
+        let path = real_path(&path).ok_or_else(|| denied(&path))?;
+        let root = self
+            .roots
+            .iter()
+            .filter_map(|root| Some((real_path(&root.path)?, root.access)))
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count());
+        match root {
+            Some((_, Access::ReadOnly)) if access == Access::ReadWrite => Err(denied(&path)),
+            Some((root, _)) if access == Access::ReadWrite && root == path => {
+                // the root itself cannot be modified but it can be created
+                if path.exists() {
+                    Err(denied(&path))
+                } else {
+                    Ok(path)
+                }
+            }
+            Some(_) => Ok(path),
+            None => Err(denied(&path)),
+        }
+    }
+
+    fn read_file<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        options: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let encoding = match options.0 {
+            Some(options) => match options.type_of() {
+                Type::String => Some(StdString::from_js(ctx, options)?),
+                Type::Object => options
+                    .as_object()
+                    .unwrap()
+                    .get::<_, Option<StdString>>("encoding")?,
+                _ => None,
+            },
+            None => None,
+        };
+        let data = fs::read(self.resolve(&path, Access::ReadOnly)?)?;
+        match encoding.as_deref() {
+            None => TypedArray::new(ctx, data).map(|array| array.into_value()),
+            Some("utf8") | Some("utf-8") => StdString::from_utf8_lossy(&data).into_js(ctx),
+            Some(encoding) => Err(Error::new_from_js_message(
+                "string",
+                "encoding",
+                format!("Unsupported encoding: \"{}\"", encoding),
+            )),
+        }
+    }
+
+    fn write_file<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        data: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let data = data.0.unwrap_or_else(|| Value::new_undefined(ctx));
+        let data = match data.type_of() {
+            Type::String => StdString::from_js(ctx, data)?.into_bytes(),
+            _ => ArrayBuffer::copy_bytes(&data).ok_or_else(|| {
+                Error::new_from_js_message(
+                    data.type_of().as_str(),
+                    "bytes",
+                    "The data should be a string, an ArrayBuffer or an ArrayBufferView",
+                )
+            })?,
+        };
+        fs::write(self.resolve(&path, Access::ReadWrite)?, data)?;
+        Ok(Value::new_undefined(ctx))
+    }
+
+    fn read_dir<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        _: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let mut names = fs::read_dir(self.resolve(&path, Access::ReadOnly)?)?
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        names.sort();
+        names.into_js(ctx)
+    }
+
+    fn stat<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        _: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let path = self.resolve(&path, Access::ReadOnly)?;
+        let metadata = fs::metadata(&path)?;
+        let stat = Object::new(ctx)?;
+        stat.set("size", metadata.len() as f64)?;
+        stat.set("mtimeMs", time_ms(metadata.modified()))?;
+        stat.set(
+            "readonly",
+            metadata.permissions().readonly() || self.resolve(&path, Access::ReadWrite).is_err(),
+        )?;
+        let is_file = metadata.is_file();
+        stat.set("isFile", Func::new("isFile", move || is_file))?;
+        let is_dir = metadata.is_dir();
+        stat.set("isDirectory", Func::new("isDirectory", move || is_dir))?;
+        Ok(stat.into_value())
+    }
+
+    fn mkdir<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        options: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let path = self.resolve(&path, Access::ReadWrite)?;
+        if recursive(&options)? {
+            fs::create_dir_all(path)?;
+        } else {
+            fs::create_dir(path)?;
+        }
+        Ok(Value::new_undefined(ctx))
+    }
+
+    fn remove<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        path: StdString,
+        options: Opt<Value<'js>>,
+        _: Opt<Value<'js>>,
+    ) -> Result<Value<'js>> {
+        let path = self.resolve(&path, Access::ReadWrite)?;
+        if !fs::metadata(&path)?.is_dir() {
+            fs::remove_file(path)?;
+        } else if recursive(&options)? {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_dir(path)?;
+        }
+        Ok(Value::new_undefined(ctx))
+    }
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn op_fn<F>(func: F) -> F
+where
+    F: for<'js> Fn(
+        Ctx<'js>,
+        Coerced<StdString>,
+        Opt<Value<'js>>,
+        Opt<Value<'js>>,
+    ) -> Result<Value<'js>>,
+{
+    func
+}
+
+/// Create the promise which is settled with result
+fn promised<'js>(ctx: Ctx<'js>, result: Result<Value<'js>>) -> Result<Value<'js>> {
+    let (promise, resolve, reject) = ctx.promise()?;
+    match result {
+        Ok(value) => resolve.call::<_, ()>((value,))?,
+        Err(error) => reject.call::<_, ()>(((&error).into_js(ctx)?,))?,
+    }
+    Ok(promise.into_value())
+}
+
+fn recursive(options: &Opt<Value>) -> Result<bool> {
+    Ok(
+        match options.0.as_ref().and_then(|options| options.as_object()) {
+            Some(options) => options
+                .get::<_, Option<bool>>("recursive")?
+                .unwrap_or(false),
+            None => false,
+        },
+    )
+}
+
+fn time_ms(time: std::io::Result<SystemTime>) -> f64 {
+    time.ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs_f64() * 1000.0)
+        .unwrap_or(f64::NAN)
+}
+
+fn denied(path: &Path) -> Error {
+    IoError::new(
+        ErrorKind::PermissionDenied,
+        format!("Access to '{}' is not allowed", path.display()),
+    )
+    .into()
+}
+
+/// Remove `.` and `..` components without accessing filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component.as_os_str()),
+        }
+    }
+    result
+}
+
+/// Resolve symbolic links of the longest existing part of normalized path
+///
+/// Returns `None` when the path goes through a dangling symbolic link
+/// because the link target may be created outside of roots.
+fn real_path(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(real) = fs::canonicalize(existing) {
+            return Some(rest.iter().rev().fold(real, |path, name| path.join(name)));
+        }
+        if fs::symlink_metadata(existing).is_ok() {
+            return None;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return Some(path.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fs::*, *};
+
+    fn with_fs<F: FnOnce(&Runtime, &Context)>(name: &str, f: F) {
+        let dir = std::env::temp_dir().join(format!("rquickjs_fs_test_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("rw")).unwrap();
+        std::fs::create_dir_all(dir.join("ro")).unwrap();
+        std::fs::write(dir.join("ro/data.txt"), "data").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        let fs = Fs::default()
+            .with_root(dir.join("rw"), Access::ReadWrite)
+            .with_root(dir.join("ro"), Access::ReadOnly);
+
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(
+            BuiltinResolver::default().with_module("fs"),
+            SyntheticLoader::default().with_module_fn("fs", move |ctx| fs.exports(ctx)),
+        );
+        ctx.with(|ctx| {
+            ctx.compile("init", "import fs from 'fs'; globalThis.fs = fs;")
+                .unwrap();
+        });
+        f(&rt, &ctx);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sync() {
+        with_fs("sync", |_, ctx| {
+            let result: Vec<StdString> = ctx.with(|ctx| {
+                ctx.eval(
+                    r#"
+                    fs.mkdirSync("a/b", { recursive: true });
+                    fs.writeFileSync("a/b/text.txt", "Hello");
+                    fs.writeFileSync("a/bytes.bin", Uint8Array.of(1, 2, 3));
+                    const stat = fs.statSync("a/bytes.bin");
+                    const result = [
+                        fs.readFileSync("a/b/text.txt", { encoding: "utf8" }),
+                        fs.readFileSync("a/bytes.bin").join(),
+                        fs.readdirSync("a").join(),
+                        [stat.size, stat.isFile(), stat.isDirectory(), stat.readonly].join(),
+                    ];
+                    fs.removeSync("a/bytes.bin");
+                    try { fs.removeSync("a"); } catch (e) { result.push("not empty"); }
+                    fs.removeSync("a", { recursive: true });
+                    result.push(String(fs.readdirSync(".").length));
+                    result
+                "#,
+                )
+                .unwrap()
+            });
+            assert_eq!(
+                result,
+                [
+                    "Hello",
+                    "1,2,3",
+                    "b,bytes.bin",
+                    "3,true,false,false",
+                    "not empty",
+                    "0"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn sandbox() {
+        with_fs("sandbox", |_, ctx| {
+            let denied: Vec<bool> = ctx.with(|ctx| {
+                ctx.eval(
+                    r#"
+                    [
+                        () => fs.readdirSync(".."),
+                        () => fs.readFileSync("../secret.txt"),
+                        () => fs.readFileSync("../ro/../secret.txt"),
+                        () => fs.writeFileSync("../ro/data.txt", ""),
+                        () => fs.removeSync("../ro/data.txt"),
+                        () => fs.removeSync("."),
+                        () => fs.readFileSync("../ro/data.txt"),
+                    ].map(op => { try { op(); return false; } catch (e) { return true; } })
+                "#,
+                )
+                .unwrap()
+            });
+            assert_eq!(denied, [true, true, true, true, true, true, false]);
+
+            #[cfg(unix)]
+            {
+                let dir = std::env::temp_dir().join("rquickjs_fs_test_sandbox");
+                std::os::unix::fs::symlink(dir.join("escaped.txt"), dir.join("rw/link.txt"))
+                    .unwrap();
+                std::os::unix::fs::symlink(dir.join("escaped"), dir.join("rw/link")).unwrap();
+                let denied: Vec<bool> = ctx.with(|ctx| {
+                    ctx.eval(
+                        r#"
+                        [
+                            () => fs.writeFileSync("link.txt", "escaped"),
+                            () => fs.mkdirSync("link/sub", { recursive: true }),
+                            () => fs.readFileSync("link.txt"),
+                        ].map(op => { try { op(); return false; } catch (e) { return true; } })
+                    "#,
+                    )
+                    .unwrap()
+                });
+                assert_eq!(denied, [true, true, true]);
+                assert!(!dir.join("escaped.txt").exists());
+                assert!(!dir.join("escaped").exists());
+            }
+        });
+    }
+
+    #[test]
+    fn promises() {
+        with_fs("promises", |rt, ctx| {
+            ctx.with(|ctx| {
+                let _: Value = ctx
+                    .eval(
+                        r#"
+                        globalThis.result = [];
+                        fs.writeFile("text.txt", "Hi")
+                            .then(() => fs.readFile("text.txt", "utf8"))
+                            .then(text => result.push(text))
+                            .then(() => fs.readFile("../secret.txt"))
+                            .catch(error => result.push(error.message.includes("not allowed")));
+                    "#,
+                    )
+                    .unwrap();
+            });
+            while rt.execute_pending_job().unwrap() {}
+            let result: StdString = ctx.with(|ctx| ctx.eval("result.join()").unwrap());
+            assert_eq!(result, "Hi,true");
+        });
+    }
+}
@@ -28,7 +28,7 @@ pub mod phf {
 }
 
 mod markers;
-pub use markers::ParallelSend;
+pub use markers::{ParallelSend, ParallelSync};
 mod result;
 pub use result::{Error, Result};
 mod safe_ref;
@@ -44,7 +44,10 @@ pub use runtime::Tokio;
 pub use runtime::{Executor, ExecutorSpawner, Idle};
 pub use runtime::{MemoryUsage, Runtime};
 mod context;
-pub use context::{intrinsic, Context, ContextBuilder, Ctx, EvalOptions, Intrinsic, MultiWith};
+pub use context::{
+    intrinsic, Clock, Context, ContextBuilder, ContextTemplate, Ctx, Deterministic, EvalOptions,
+    Intrinsic, Lockdown, MultiWith, SafeIntrinsic, VirtualClock,
+};
 mod value;
 pub use value::*;
 mod persistent;
@@ -85,13 +88,57 @@ mod loader;
 
 #[cfg(feature = "loader")]
 pub use loader::{
-    BuiltinLoader, BuiltinResolver, Bundle, Compile, FileResolver, HasByteCode, Loader,
-    ModuleLoader, Resolver, ScriptLoader,
+    ArchiveFs, BuiltinLoader, BuiltinResolver, Bundle, BytecodeCache, Compile, FileResolver,
+    HasByteCode, HotReload, JsonLoader, Loader, MemoryFs, ModuleFs, ModuleInfo, ModuleLoader,
+    ModuleState, RequestedModulesIter, Resolver, ScaBundleData, ScriptLoader, StdFs,
+    SyntheticLoader,
 };
 
+#[cfg(all(feature = "loader", feature = "phf"))]
+pub use loader::PhfBundleData;
+
 #[cfg(feature = "dyn-load")]
 pub use loader::NativeLoader;
 
+#[cfg(feature = "commonjs")]
+pub use loader::{CommonJs, CommonJsLoader};
+
+#[cfg(feature = "bundle")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "bundle")))]
+pub mod bundle;
+
+#[cfg(feature = "console")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "console")))]
+pub mod console;
+
+#[cfg(feature = "encoding")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "encoding")))]
+pub mod encoding;
+
+#[cfg(feature = "url")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "url")))]
+pub mod url;
+
+#[cfg(feature = "crypto")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "crypto")))]
+pub mod crypto;
+
+#[cfg(feature = "fs")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fs")))]
+pub mod fs;
+
+#[cfg(feature = "timers")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "timers")))]
+pub mod timers;
+
+#[cfg(feature = "fetch")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "fetch")))]
+pub mod fetch;
+
+#[cfg(feature = "pool")]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "pool")))]
+pub mod pool;
+
 #[cfg(test)]
 pub(crate) fn test_with<F, R>(func: F) -> R
 where
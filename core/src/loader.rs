@@ -1,6 +1,19 @@
-use crate::{qjs, Ctx, Error, Loaded, Module, Result, Script};
+use crate::{qjs, Ctx, Error, Function, Loaded, Module, Object, Result, Script, StdString};
 use relative_path::RelativePath;
-use std::{ffi::CStr, ptr};
+use std::{
+    env,
+    ffi::CStr,
+    fmt::Write,
+    path::Path,
+    ptr::{self, NonNull},
+};
+
+mod module_registry;
+pub(crate) use module_registry::ModuleRegistry;
+pub use module_registry::{ModuleInfo, ModuleState, RequestedModulesIter};
+
+mod module_fs;
+pub use module_fs::{ArchiveFs, MemoryFs, ModuleFs, StdFs};
 
 mod file_resolver;
 pub use file_resolver::FileResolver;
@@ -8,6 +21,20 @@ pub use file_resolver::FileResolver;
 mod script_loader;
 pub use script_loader::ScriptLoader;
 
+mod bytecode_cache;
+pub use bytecode_cache::BytecodeCache;
+
+mod json_loader;
+pub use json_loader::JsonLoader;
+
+mod synthetic_loader;
+pub use synthetic_loader::SyntheticLoader;
+
+#[cfg(feature = "commonjs")]
+mod commonjs;
+#[cfg(feature = "commonjs")]
+pub use commonjs::{CommonJs, CommonJsLoader};
+
 #[cfg(feature = "dyn-load")]
 mod native_loader;
 #[cfg(feature = "dyn-load")]
@@ -25,6 +52,9 @@ pub use module_loader::ModuleLoader;
 mod compile;
 pub use compile::Compile;
 
+mod hot_reload;
+pub use hot_reload::HotReload;
+
 mod bundle;
 #[cfg(feature = "phf")]
 pub use bundle::PhfBundleData;
@@ -62,7 +92,10 @@ pub trait Loader<S = ()> {
     fn load<'js>(&mut self, ctx: Ctx<'js>, name: &str) -> Result<Module<'js, Loaded<S>>>;
 }
 
-struct LoaderOpaque {
+/// The initializer of `import.meta` objects
+pub(crate) type ImportMetaInit = dyn for<'js> Fn(Ctx<'js>, &str, Object<'js>) -> Result<()>;
+
+pub(crate) struct LoaderOpaque {
     resolver: Box<dyn Resolver>,
     loader: Box<dyn Loader>,
 }
@@ -88,6 +121,10 @@ impl LoaderHolder {
         })))
     }
 
+    pub(crate) fn opaque_ptr(&self) -> NonNull<LoaderOpaque> {
+        unsafe { NonNull::new_unchecked(self.0) }
+    }
+
     pub(crate) fn set_to_runtime(&self, rt: *mut qjs::JSRuntime) {
         unsafe {
             qjs::JS_SetModuleLoaderFunc(
@@ -99,9 +136,22 @@ impl LoaderHolder {
         }
     }
 
+    /// Resolve module name using the resolver of runtime
+    pub(crate) fn resolve<'js>(ctx: Ctx<'js>, base: &str, name: &str) -> Result<String> {
+        match unsafe { ctx.get_opaque() }.loader {
+            // Only resolver is borrowed here so it is safe to resolve modules while loading
+            Some(opaque) => unsafe { (*opaque.as_ptr()).resolver.resolve(ctx, base, name) },
+            None => Err(Error::new_resolving_message(
+                base,
+                name,
+                "No module loader was set",
+            )),
+        }
+    }
+
     #[inline]
     fn normalize<'js>(
-        opaque: &mut LoaderOpaque,
+        resolver: &mut dyn Resolver,
         ctx: Ctx<'js>,
         base: &CStr,
         name: &CStr,
@@ -109,7 +159,9 @@ impl LoaderHolder {
         let base = base.to_str()?;
         let name = name.to_str()?;
 
-        let name = opaque.resolver.resolve(ctx, base, name)?;
+        let path = resolver.resolve(ctx, base, name)?;
+        unsafe { ctx.get_modules() }.add_request(base, name, &path);
+        let name = path;
 
         // We should transfer ownership of this string to QuickJS
         Ok(
@@ -128,9 +180,9 @@ impl LoaderHolder {
         let ctx = Ctx::from_ptr(ctx);
         let base = CStr::from_ptr(base);
         let name = CStr::from_ptr(name);
-        let loader = &mut *(opaque as *mut LoaderOpaque);
+        let resolver = &mut *(*(opaque as *mut LoaderOpaque)).resolver;
 
-        Self::normalize(loader, ctx, base, name).unwrap_or_else(|error| {
+        Self::normalize(resolver, ctx, base, name).unwrap_or_else(|error| {
             error.throw(ctx);
             ptr::null_mut()
         })
@@ -138,13 +190,46 @@ impl LoaderHolder {
 
     #[inline]
     fn load<'js>(
-        opaque: &mut LoaderOpaque,
+        loader: &mut dyn Loader,
         ctx: Ctx<'js>,
         name: &CStr,
     ) -> Result<*mut qjs::JSModuleDef> {
         let name = name.to_str()?;
 
-        Ok(opaque.loader.load(ctx, name)?.into_module_def())
+        let module = match loader.load(ctx, name) {
+            Ok(module) => module,
+            Err(error) => {
+                unsafe { ctx.get_modules() }.set_state(name, ModuleState::Failed);
+                return Err(error);
+            }
+        };
+        Self::loaded(ctx, name, &module)?;
+        Ok(module.into_module_def())
+    }
+
+    /// Register loaded module and initialize its `import.meta`
+    fn loaded<'js, S>(ctx: Ctx<'js>, name: &str, module: &Module<'js, Loaded<S>>) -> Result<()> {
+        unsafe { ctx.get_modules() }.set_state(name, ModuleState::Loaded);
+        Self::init_meta(ctx, name, module.get_meta()?)
+    }
+
+    /// Set default properties of `import.meta` and call the user-defined initializer
+    fn init_meta<'js>(ctx: Ctx<'js>, name: &str, meta: Object<'js>) -> Result<()> {
+        meta.set("url", module_url(name))?;
+
+        let resolve = {
+            let base = StdString::from(name);
+            Function::new(ctx, move |ctx: Ctx, specifier: StdString| {
+                ctx.resolve_module(&base, &specifier)
+            })?
+        };
+        resolve.set_name("resolve")?;
+        meta.set("resolve", resolve)?;
+
+        if let Some(init) = &unsafe { ctx.get_opaque() }.import_meta_init {
+            init(ctx, name, meta)?;
+        }
+        Ok(())
     }
 
     unsafe extern "C" fn load_raw(
@@ -154,7 +239,7 @@ impl LoaderHolder {
     ) -> *mut qjs::JSModuleDef {
         let ctx = Ctx::from_ptr(ctx);
         let name = CStr::from_ptr(name);
-        let loader = &mut *(opaque as *mut LoaderOpaque);
+        let loader = &mut *(*(opaque as *mut LoaderOpaque)).loader;
 
         Self::load(loader, ctx, name).unwrap_or_else(|error| {
             error.throw(ctx);
@@ -163,6 +248,51 @@ impl LoaderHolder {
     }
 }
 
+/// Convert resolved module name to URL
+///
+/// The names which is already URLs is kept as is, the names which looks like file paths is converted to `file:` URLs.
+/// The bare names of builtin or synthetic modules (i.e. `fs`) is kept as is too.
+fn module_url(name: &str) -> String {
+    if let Some(pos) = name.find("://") {
+        let scheme = &name[..pos];
+        if !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        {
+            return name.into();
+        }
+    }
+
+    let path = Path::new(name);
+    if !path.is_absolute() && !name.contains(&['/', '\\'][..]) && path.extension().is_none() {
+        return name.into();
+    }
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.into())
+    };
+    let path = path.to_string_lossy().replace('\\', "/");
+
+    let mut url = StdString::from("file://");
+    if !path.starts_with('/') {
+        // windows paths starts with drive letter
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                url.push(byte as char)
+            }
+            _ => write!(url, "%{:02X}", byte).unwrap(),
+        }
+    }
+    url
+}
+
 fn resolve_simple(base: &str, name: &str) -> String {
     if name.starts_with('.') {
         let path = RelativePath::new(base);
@@ -292,17 +422,33 @@ macro_rules! generic_loader {
 }
 
 generic_loader! {
-    ScriptLoader: Script,
+    ScriptLoader<F>: Script {
+        F: ModuleFs,
+    },
     #[cfg(feature = "dyn-load")]
     NativeLoader: Native,
     BuiltinLoader: Script,
+    BytecodeCache<F>: Script {
+        F: ModuleFs,
+    },
     ModuleLoader: Native,
+    JsonLoader<F>: Native {
+        F: ModuleFs,
+    },
+    SyntheticLoader: Native,
+    #[cfg(feature = "commonjs")]
+    CommonJsLoader<F>: Native {
+        F: ModuleFs + crate::ParallelSend + crate::ParallelSync + 'static,
+    },
     Bundle<L>: Script {
         Self: Loader<Script>,
     },
     Compile<L>: Script {
         L: Loader<Script>,
     },
+    HotReload<L>: Script {
+        Self: Loader<Script>,
+    },
 }
 
 #[cfg(test)]
@@ -345,6 +491,107 @@ mod test {
         }
     }
 
+    #[test]
+    fn import_meta() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(
+            BuiltinResolver::default()
+                .with_module("lib/meta.js")
+                .with_module("lib/other.js"),
+            BuiltinLoader::default().with_module(
+                "lib/meta.js",
+                r#"
+                  globalThis.url = import.meta.url;
+                  globalThis.resolved = import.meta.resolve("./other.js");
+                  globalThis.name = import.meta.name;
+                "#,
+            ),
+        );
+        rt.set_import_meta_initializer(|_ctx, name, meta| meta.set("name", name));
+        ctx.with(|ctx| {
+            ctx.compile("main", "import 'lib/meta.js';").unwrap();
+            let url = ctx.eval::<StdString, _>("url").unwrap();
+            assert!(url.starts_with("file:///"));
+            assert!(url.ends_with("/lib/meta.js"));
+            assert_eq!(
+                ctx.eval::<StdString, _>("resolved").unwrap(),
+                "lib/other.js"
+            );
+            assert_eq!(ctx.eval::<StdString, _>("name").unwrap(), "lib/meta.js");
+        });
+        assert_eq!(super::module_url("http://host/a.js"), "http://host/a.js");
+        assert_eq!(super::module_url("/lib/a b.js"), "file:///lib/a%20b.js");
+        assert_eq!(
+            super::module_url("/lib/a.js?v=1&w=2"),
+            "file:///lib/a.js%3Fv%3D1%26w%3D2"
+        );
+        assert_eq!(super::module_url("fs"), "fs");
+        assert_eq!(super::module_url("node:fs"), "node:fs");
+        assert!(super::module_url("lib/a.js").starts_with("file:///"));
+    }
+
+    #[test]
+    fn module_registry() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(
+            BuiltinResolver::default()
+                .with_module("a")
+                .with_module("b")
+                .with_module("c"),
+            BuiltinLoader::default()
+                .with_module("a", "import './b'; export const a = 1;")
+                .with_module("b", "import './a'; export const b = 1;"),
+        );
+        ctx.with(|ctx| {
+            let module = ctx.compile("main", "import { a } from 'a';").unwrap();
+            assert_eq!(
+                module.requested_modules().unwrap(),
+                vec![("a".into(), "a".into())]
+            );
+            assert!(ctx.compile("other", "import 'c';").is_err());
+        });
+
+        let modules = rt.modules().collect::<Vec<_>>();
+        let names = modules.iter().map(|info| info.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["main", "a", "b", "other", "c"]);
+        assert_eq!(modules[0].state(), ModuleState::Evaluated);
+        assert_eq!(modules[1].state(), ModuleState::Evaluated);
+        assert_eq!(modules[2].dependencies().collect::<Vec<_>>(), ["a"]);
+        assert_eq!(modules[2].state(), ModuleState::Evaluated);
+        assert_eq!(modules[4].state(), ModuleState::Failed);
+    }
+
+    #[test]
+    fn module_registry_contexts() {
+        let rt = Runtime::new().unwrap();
+        rt.set_loader(
+            BuiltinResolver::default().with_module("a"),
+            BuiltinLoader::default().with_module("a", "export const a = 1;"),
+        );
+        let ctx1 = Context::full(&rt).unwrap();
+        let ctx2 = Context::full(&rt).unwrap();
+        ctx1.with(|ctx| {
+            ctx.compile("main", "import 'a';").unwrap();
+        });
+        ctx2.with(|ctx| {
+            assert_eq!(ctx.modules().len(), 0);
+            Module::new(ctx, "main", "import 'a';").unwrap();
+            let modules = ctx.modules().collect::<Vec<_>>();
+            assert_eq!(modules[0].name(), "main");
+            assert_eq!(modules[0].state(), ModuleState::Loaded);
+        });
+        ctx1.with(|ctx| {
+            let modules = ctx.modules().collect::<Vec<_>>();
+            assert_eq!(modules[0].state(), ModuleState::Evaluated);
+        });
+        assert_eq!(rt.modules().len(), 4);
+
+        drop(ctx1);
+        assert_eq!(rt.modules().len(), 2);
+    }
+
     #[test]
     fn custom_loader() {
         let rt = Runtime::new().unwrap();
@@ -363,6 +610,118 @@ mod test {
         })
     }
 
+    #[test]
+    fn synthetic_loader() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let mut config = std::collections::HashMap::new();
+        config.insert("answer", 42);
+        rt.set_loader(
+            BuiltinResolver::default()
+                .with_module("config")
+                .with_module("readme"),
+            SyntheticLoader::default()
+                .with_module("config", config)
+                .with_text("readme", "Hello!"),
+        );
+        ctx.with(|ctx| {
+            ctx.compile(
+                "loader",
+                r#"
+                  import { answer } from "config";
+                  import readme from "readme";
+                  globalThis.result = `${readme} ${answer}`;
+                "#,
+            )
+            .unwrap();
+            let result: StdString = ctx.globals().get("result").unwrap();
+            assert_eq!(result, "Hello! 42");
+        })
+    }
+
+    #[test]
+    fn synthetic_loader_contexts() {
+        let rt = Runtime::new().unwrap();
+        rt.set_loader(
+            BuiltinResolver::default().with_module("readme"),
+            SyntheticLoader::default().with_text("readme", "Hello!"),
+        );
+        for _ in 0..2 {
+            let ctx = Context::full(&rt).unwrap();
+            ctx.with(|ctx| {
+                ctx.compile(
+                    "loader",
+                    "import readme from 'readme'; globalThis.result = readme;",
+                )
+                .unwrap();
+                let result: StdString = ctx.globals().get("result").unwrap();
+                assert_eq!(result, "Hello!");
+            })
+        }
+    }
+
+    #[test]
+    fn json_loader() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let fs = std::rc::Rc::new(
+            MemoryFs::default()
+                .with_file("data/config.json", r#"{ "answer": 42, "list": [1, 2] }"#)
+                .with_file("data/broken.json", "{ answer: }"),
+        );
+        rt.set_loader(
+            FileResolver::default()
+                .with_path("data")
+                .with_pattern("{}.json")
+                .with_fs(fs.clone()),
+            JsonLoader::default().with_fs(fs),
+        );
+        ctx.with(|ctx| {
+            ctx.compile(
+                "loader",
+                r#"
+                  import config from "config";
+                  globalThis.result = config.answer + config.list.length;
+                "#,
+            )
+            .unwrap();
+            let result: i32 = ctx.globals().get("result").unwrap();
+            assert_eq!(result, 44);
+
+            assert!(ctx.compile("broken", "import data from 'broken';").is_err());
+        })
+    }
+
+    #[test]
+    fn memory_fs_loader() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let fs = std::rc::Rc::new(
+            MemoryFs::default()
+                .with_file("lib/n.js", "export const n = 123;")
+                .with_file("lib/s.js", "export { n as s } from './n';"),
+        );
+        rt.set_loader(
+            FileResolver::default().with_path("lib").with_fs(fs.clone()),
+            ScriptLoader::default().with_fs(fs),
+        );
+        ctx.with(|ctx| {
+            let module = ctx
+                .compile(
+                    "loader",
+                    r#"
+                      import { s } from "s";
+                      export default s;
+                    "#,
+                )
+                .unwrap();
+            #[cfg(feature = "exports")]
+            assert_eq!(module.get::<_, i32>("default").unwrap(), 123);
+            #[cfg(not(feature = "exports"))]
+            let _ = module;
+        })
+    }
+
     #[test]
     #[should_panic(expected = "Unable to resolve")]
     fn resolving_error() {
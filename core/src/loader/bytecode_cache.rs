@@ -0,0 +1,205 @@
+use super::{ModuleFs, ScriptLoader, StdFs};
+use crate::{value::bytecode::checksum, Ctx, Loaded, Loader, Module, Result, Script};
+use std::{
+    convert::TryInto,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The magic bytes of cache files
+const MAGIC: &[u8; 4] = b"RQJC";
+
+/// The on-disk bytecode cache for script modules
+///
+/// This loader wraps the [`ScriptLoader`] and stores the bytecode of compiled modules in the cache directory.
+/// The cached bytecode is used instead of compiling the sources when the hash of source matches.
+/// The modules is transparently recompiled when the cache entry is outdated or the bytecode cannot be read,
+/// i.e. when it is corrupted or was compiled by another version of QuickJS.
+///
+/// ```no_run
+/// # use rquickjs::{Runtime, BytecodeCache, FileResolver, ScriptLoader};
+/// let rt = Runtime::new().unwrap();
+/// rt.set_loader(
+///     FileResolver::default(),
+///     BytecodeCache::new(ScriptLoader::default(), "target/js-cache"),
+/// );
+/// ```
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug)]
+pub struct BytecodeCache<F = StdFs> {
+    loader: ScriptLoader<F>,
+    dir: PathBuf,
+}
+
+impl<F> BytecodeCache<F> {
+    /// Create bytecode cache which stores compiled modules in the directory
+    ///
+    /// The directory will be created when it does not exists.
+    pub fn new<D: Into<PathBuf>>(loader: ScriptLoader<F>, dir: D) -> Self {
+        Self {
+            loader,
+            dir: dir.into(),
+        }
+    }
+
+    /// Get the cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Remove all cached bytecodes
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.is_dir() {
+            for entry in fs::read_dir(&self.dir)? {
+                let path = entry?.path();
+                if path.extension().map(|ext| ext == "jsc").unwrap_or(false) {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, path: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.jsc", checksum(path.as_bytes())))
+    }
+}
+
+impl<F: ModuleFs> Loader<Script> for BytecodeCache<F> {
+    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Script>>> {
+        let source = self.loader.read_source(path)?;
+        let source_hash = checksum(&source);
+        let entry_path = self.entry_path(path);
+
+        if let Some(bytecode) = fs::read(&entry_path)
+            .ok()
+            .and_then(|data| read_entry(data, path, source_hash))
+        {
+            if let Ok(module) = Module::read_object(ctx, bytecode) {
+                return Ok(module);
+            }
+        }
+
+        let module = Module::new(ctx, path, source)?;
+        // The caching is optional so failed writes should not break loading
+        let _ = write_entry(
+            &self.dir,
+            &entry_path,
+            path,
+            source_hash,
+            &module.write_object(false)?,
+        );
+        Ok(module)
+    }
+}
+
+/// The cache entry layout:
+///
+/// - magic
+/// - length of module path (u32 le)
+/// - module path
+/// - hash of source (u64 le)
+/// - bytecode (with versioned header)
+fn write_entry(
+    dir: &Path,
+    entry_path: &Path,
+    path: &str,
+    source_hash: u64,
+    bytecode: &[u8],
+) -> Result<()> {
+    let mut data = Vec::with_capacity(MAGIC.len() + 4 + path.len() + 8 + bytecode.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(path.len() as u32).to_le_bytes());
+    data.extend_from_slice(path.as_bytes());
+    data.extend_from_slice(&source_hash.to_le_bytes());
+    data.extend_from_slice(bytecode);
+
+    fs::create_dir_all(dir)?;
+    // Write to temporary file first to avoid reading of partially written entries
+    let temp_path = entry_path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&temp_path, data)?;
+    if let Err(error) = fs::rename(&temp_path, entry_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+fn read_entry(data: Vec<u8>, path: &str, source_hash: u64) -> Option<Vec<u8>> {
+    let mut input = data.as_slice();
+    if take(&mut input, MAGIC.len())? != MAGIC {
+        return None;
+    }
+    let path_len = u32::from_le_bytes(take(&mut input, 4)?.try_into().ok()?) as usize;
+    if take(&mut input, path_len)? != path.as_bytes() {
+        return None;
+    }
+    if u64::from_le_bytes(take(&mut input, 8)?.try_into().ok()?) != source_hash {
+        return None;
+    }
+    Some(input.to_vec())
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Some(head)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::{fs, path::Path};
+
+    struct TestResolver;
+
+    impl Resolver for TestResolver {
+        fn resolve<'js>(&mut self, _ctx: Ctx<'js>, _base: &str, name: &str) -> Result<StdString> {
+            Ok(name.into())
+        }
+    }
+
+    fn run(dir: &Path, source: &str) -> i32 {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(
+            TestResolver,
+            BytecodeCache::new(
+                ScriptLoader::default().with_fs(MemoryFs::default().with_file("n.js", source)),
+                dir,
+            ),
+        );
+        ctx.with(|ctx| {
+            ctx.compile("main", "import 'n.js';").unwrap();
+            ctx.eval("n").unwrap()
+        })
+    }
+
+    #[test]
+    fn bytecode_cache() {
+        let dir = std::env::temp_dir().join("rquickjs_bytecode_cache_test");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(run(&dir, "globalThis.n = 1;"), 1);
+        let entries = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(run(&dir, "globalThis.n = 1;"), 1);
+
+        // changed source
+        assert_eq!(run(&dir, "globalThis.n = 2;"), 2);
+
+        // corrupted entry
+        let mut data = fs::read(&entries[0]).unwrap();
+        let len = data.len();
+        data[len - 1] ^= 0xff;
+        fs::write(&entries[0], data).unwrap();
+        assert_eq!(run(&dir, "globalThis.n = 2;"), 2);
+    }
+}
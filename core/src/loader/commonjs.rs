@@ -0,0 +1,363 @@
+use super::{check_extensions, ModuleFs, StdFs};
+use crate::{
+    qjs, Ctx, Error, Function, Loaded, Loader, Module, Mut, Native, Object, ParallelSend,
+    ParallelSync, Persistent, Ref, Result, StdString, This, Value,
+};
+use relative_path::RelativePath;
+use std::ffi::CString;
+
+/// The wrapper which provides module scope like Node.js does
+///
+/// It is placed on the same line with module code to keep line numbers untouched.
+const WRAPPER_HEAD: &[u8] = b"(function (exports, require, module, __filename, __dirname) {";
+const WRAPPER_TAIL: &[u8] = b"\n})";
+
+/// The filesystem which is shared between `require` functions of modules
+trait SharedFs: ModuleFs + ParallelSend + ParallelSync + 'static {}
+
+impl<F> SharedFs for F where F: ModuleFs + ParallelSend + ParallelSync + 'static {}
+
+type FsRef = Ref<dyn SharedFs>;
+
+/// The state of CommonJS modules in context
+///
+/// It is kept in the user data of context so the scripts cannot replace the cache.
+struct State {
+    cache: Persistent<Object<'static>>,
+    fs: Mut<FsRef>,
+}
+
+fn state<'js>(ctx: Ctx<'js>) -> Result<&'js State> {
+    if let Some(state) = ctx.userdata::<State>() {
+        return Ok(state);
+    }
+    let state = State {
+        cache: Persistent::save(ctx, Object::new(ctx)?),
+        fs: Mut::new(Ref::new(StdFs)),
+    };
+    // the state is created once per context here
+    let _ = ctx.store_userdata(state);
+    ctx.userdata::<State>().ok_or(Error::Unknown)
+}
+
+fn cache<'js>(ctx: Ctx<'js>) -> Result<Object<'js>> {
+    state(ctx)?.cache.clone().restore(ctx)
+}
+
+/// CommonJS modules support
+///
+/// The modules is resolved using the resolver which is set via [`Runtime::set_loader`](crate::Runtime::set_loader)
+/// and cached by resolved names like Node.js does.
+/// Unlike Node.js the cache is kept by host and is not exposed to the scripts.
+/// Cyclic requires receives partially filled exports of modules which is not loaded yet.
+///
+/// ```no_run
+/// # use rquickjs::{Runtime, Context, CommonJs, CommonJsLoader, FileResolver};
+/// let rt = Runtime::new().unwrap();
+/// rt.set_loader(
+///     FileResolver::default().with_pattern("{}.cjs"),
+///     CommonJsLoader::default(),
+/// );
+/// let ctx = Context::full(&rt).unwrap();
+/// ctx.with(|ctx| {
+///     CommonJs::init(ctx, "main.js").unwrap();
+///     let _: () = ctx.eval(r#"const lib = require("./lib");"#).unwrap();
+/// });
+/// ```
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "commonjs")))]
+pub struct CommonJs;
+
+impl CommonJs {
+    /// Install `require`, `module`, `exports`, `__filename` and `__dirname` globals
+    ///
+    /// The `filename` is used as the name of main module to resolve relative requires.
+    pub fn init<'js>(ctx: Ctx<'js>, filename: &str) -> Result<()> {
+        Self::init_with_fs(ctx, filename, StdFs)
+    }
+
+    /// Install `require`, `module`, `exports`, `__filename` and `__dirname` globals
+    /// which reads the modules using the specified filesystem
+    ///
+    /// The filesystem is also used by [`CommonJs::require`] and [`CommonJs::load`] in this context.
+    pub fn init_with_fs<'js, F>(ctx: Ctx<'js>, filename: &str, fs: F) -> Result<()>
+    where
+        F: ModuleFs + ParallelSend + ParallelSync + 'static,
+    {
+        let fs: FsRef = Ref::new(fs);
+        *state(ctx)?.fs.lock() = fs.clone();
+        let module = new_module(ctx, filename)?;
+        cache(ctx)?.set(filename, module.clone())?;
+
+        let globals = ctx.globals();
+        globals.set("exports", module.get::<_, Value>("exports")?)?;
+        globals.set("module", module)?;
+        globals.set("require", new_require(ctx, &fs, filename)?)?;
+        globals.set("__filename", filename)?;
+        globals.set("__dirname", dirname(filename))?;
+        Ok(())
+    }
+
+    /// Require module by name like `require(name)` called from module `base` does
+    pub fn require<'js>(ctx: Ctx<'js>, base: &str, name: &str) -> Result<Value<'js>> {
+        let path = ctx.resolve_module(base, name)?;
+        Self::load(ctx, &path)
+    }
+
+    /// Load module by resolved path and returns its exports
+    ///
+    /// The cached exports will be returned when module already loaded or being loading.
+    /// The module is read using the filesystem of context (see [`CommonJs::init_with_fs`]).
+    pub fn load<'js>(ctx: Ctx<'js>, path: &str) -> Result<Value<'js>> {
+        let fs = state(ctx)?.fs.lock().clone();
+        load(ctx, &fs, path)
+    }
+}
+
+fn require<'js>(ctx: Ctx<'js>, fs: &FsRef, base: &str, name: &str) -> Result<Value<'js>> {
+    let path = ctx.resolve_module(base, name)?;
+    load(ctx, fs, &path)
+}
+
+fn load<'js>(ctx: Ctx<'js>, fs: &FsRef, path: &str) -> Result<Value<'js>> {
+    let cache = cache(ctx)?;
+    if let Some(module) = cache.get::<_, Option<Object>>(path)? {
+        return module.get("exports");
+    }
+
+    let module = new_module(ctx, path)?;
+    // Module should be cached before executing to handle cyclic requires
+    cache.set(path, module.clone())?;
+    if let Err(error) = exec(ctx, fs, &module, path) {
+        cache.remove(path)?;
+        return Err(error);
+    }
+    module.set("loaded", true)?;
+    module.get("exports")
+}
+
+fn exec<'js>(ctx: Ctx<'js>, fs: &FsRef, module: &Object<'js>, path: &str) -> Result<()> {
+    let source = fs.read(path)?;
+
+    if RelativePath::new(path).extension() == Some("json") {
+        return module.set("exports", ctx.json_parse::<Value, _>(source)?);
+    }
+
+    let mut wrapped = Vec::with_capacity(WRAPPER_HEAD.len() + source.len() + WRAPPER_TAIL.len());
+    wrapped.extend_from_slice(WRAPPER_HEAD);
+    wrapped.extend(source);
+    wrapped.extend_from_slice(WRAPPER_TAIL);
+
+    let file_name = CString::new(path)?;
+    // CommonJS modules is evaluated in sloppy mode
+    let func = unsafe {
+        let value = ctx.eval_raw(wrapped, &file_name, qjs::JS_EVAL_TYPE_GLOBAL as _)?;
+        Function::from_value(Value::from_js_value(ctx, value))?
+    };
+
+    let exports: Value = module.get("exports")?;
+    func.call((
+        This(exports.clone()),
+        exports,
+        new_require(ctx, fs, path)?,
+        module.clone(),
+        path,
+        dirname(path),
+    ))
+}
+
+fn new_module<'js>(ctx: Ctx<'js>, path: &str) -> Result<Object<'js>> {
+    let module = Object::new(ctx)?;
+    module.set("id", path)?;
+    module.set("filename", path)?;
+    module.set("loaded", false)?;
+    module.set("exports", Object::new(ctx)?)?;
+    Ok(module)
+}
+
+fn new_require<'js>(ctx: Ctx<'js>, fs: &FsRef, base: &str) -> Result<Function<'js>> {
+    let require = {
+        let base = StdString::from(base);
+        let fs = fs.clone();
+        Function::new(ctx, move |ctx, name: StdString| {
+            require(ctx, &fs, &base, &name)
+        })?
+    };
+    require.set_name("require")?;
+
+    let resolve = {
+        let base = StdString::from(base);
+        Function::new(ctx, move |ctx: Ctx, name: StdString| {
+            ctx.resolve_module(&base, &name)
+        })?
+    };
+    resolve.set_name("resolve")?;
+
+    require.as_object().set("resolve", resolve)?;
+    Ok(require)
+}
+
+fn dirname(path: &str) -> &str {
+    RelativePath::new(path)
+        .parent()
+        .map(|dir| dir.as_str())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or(".")
+}
+
+/// The CommonJS module loader
+///
+/// This loader allows ES modules to import CommonJS modules. The `module.exports` becomes the `default` export of module.
+/// The modules and its nested requires is read using the filesystem which is set via [`CommonJsLoader::with_fs`].
+/// Note that [`FileResolver`](crate::FileResolver) should be configured to find such modules, i.e. using `with_pattern("{}.cjs")`.
+///
+/// This loader can be used as the nested backing loader in user-defined loaders.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "commonjs")))]
+#[derive(Debug)]
+pub struct CommonJsLoader<F = StdFs> {
+    extensions: Vec<String>,
+    fs: Ref<F>,
+}
+
+impl<F> CommonJsLoader<F> {
+    /// Use the specified filesystem to read modules
+    pub fn with_fs<G: ModuleFs>(self, fs: G) -> CommonJsLoader<G> {
+        CommonJsLoader {
+            extensions: self.extensions,
+            fs: Ref::new(fs),
+        }
+    }
+
+    /// Add CommonJS module file extension
+    pub fn add_extension<X: Into<String>>(&mut self, extension: X) -> &mut Self {
+        self.extensions.push(extension.into());
+        self
+    }
+
+    /// Add CommonJS module file extension
+    #[must_use]
+    pub fn with_extension<X: Into<String>>(mut self, extension: X) -> Self {
+        self.add_extension(extension);
+        self
+    }
+}
+
+impl Default for CommonJsLoader {
+    fn default() -> Self {
+        Self {
+            extensions: vec!["cjs".into()],
+            fs: Ref::new(StdFs),
+        }
+    }
+}
+
+impl<F> Loader<Native> for CommonJsLoader<F>
+where
+    F: ModuleFs + ParallelSend + ParallelSync + 'static,
+{
+    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Native>>> {
+        if !check_extensions(path, &self.extensions) {
+            return Err(Error::new_loading(path));
+        }
+
+        let fs: FsRef = self.fs.clone();
+        let exports = load(ctx, &fs, path)?;
+        Module::new_default(ctx, path, exports)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::{fs, path::PathBuf};
+
+    struct TestResolver(PathBuf);
+
+    impl Resolver for TestResolver {
+        fn resolve<'js>(&mut self, _ctx: Ctx<'js>, _base: &str, name: &str) -> Result<StdString> {
+            Ok(self.0.join(name).display().to_string())
+        }
+    }
+
+    #[test]
+    fn cyclic_require() {
+        let dir = std::env::temp_dir().join("rquickjs_commonjs_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a.js"),
+            r#"
+              exports.loaded = false;
+              exports.b = require("b.js").a_loaded;
+              exports.loaded = true;
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.js"),
+            r#"
+              module.exports = { a_loaded: require("a.js").loaded };
+            "#,
+        )
+        .unwrap();
+
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(TestResolver(dir), CommonJsLoader::default());
+        ctx.with(|ctx| {
+            CommonJs::init(ctx, "main.js").unwrap();
+            let a: Object = ctx.eval(r#"require("a.js")"#).unwrap();
+            assert!(a.get::<_, bool>("loaded").unwrap());
+            assert!(!a.get::<_, bool>("b").unwrap());
+        })
+    }
+
+    #[test]
+    fn hidden_cache() {
+        let fs =
+            std::sync::Arc::new(MemoryFs::default().with_file("lib.js", "exports.real = true;"));
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(TestResolver(PathBuf::new()), CommonJsLoader::default());
+        ctx.with(|ctx| {
+            CommonJs::init_with_fs(ctx, "main.js", fs).unwrap();
+            let result: Vec<bool> = ctx
+                .eval(
+                    r#"
+                    globalThis.__commonjs_cache = { "lib.js": { exports: { real: false } } };
+                    [require("lib.js").real, require.cache === undefined]
+                "#,
+                )
+                .unwrap();
+            assert_eq!(result, [true, true]);
+            // the static entry point uses the filesystem of context too
+            let lib = Object::from_value(CommonJs::load(ctx, "lib.js").unwrap()).unwrap();
+            assert!(lib.get::<_, bool>("real").unwrap());
+        })
+    }
+
+    #[test]
+    fn memory_fs() {
+        let fs = std::sync::Arc::new(
+            MemoryFs::default()
+                .with_file(
+                    "lib/a.cjs",
+                    "module.exports = { value: require('./b').value + 1 };",
+                )
+                .with_file("lib/b.cjs", "exports.value = 41;"),
+        );
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        rt.set_loader(
+            FileResolver::default()
+                .with_path("lib")
+                .with_pattern("{}.cjs")
+                .with_fs(fs.clone()),
+            CommonJsLoader::default().with_fs(fs),
+        );
+        ctx.with(|ctx| {
+            ctx.compile("main", "import a from 'a'; globalThis.result = a.value;")
+                .unwrap();
+            let result: i32 = ctx.globals().get("result").unwrap();
+            assert_eq!(result, 42);
+        })
+    }
+}
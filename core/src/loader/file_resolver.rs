@@ -1,17 +1,30 @@
+use super::{ModuleFs, StdFs};
 use crate::{Ctx, Error, Resolver, Result};
 use relative_path::{RelativePath, RelativePathBuf};
 
 /// The file module resolver
 ///
+/// The files is looked up in the real filesystem by default but any [`ModuleFs`] can be used instead.
+///
 /// This resolver can be used as the nested backing resolver in user-defined resolvers.
 #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
 #[derive(Debug)]
-pub struct FileResolver {
+pub struct FileResolver<F = StdFs> {
     paths: Vec<RelativePathBuf>,
     patterns: Vec<String>,
+    fs: F,
 }
 
-impl FileResolver {
+impl<F> FileResolver<F> {
+    /// Use the specified filesystem to look up modules
+    pub fn with_fs<G: ModuleFs>(self, fs: G) -> FileResolver<G> {
+        FileResolver {
+            paths: self.paths,
+            patterns: self.patterns,
+            fs,
+        }
+    }
+
     /// Add search path for modules
     pub fn add_path<P: Into<RelativePathBuf>>(&mut self, path: P) -> &mut Self {
         self.paths.push(path.into());
@@ -77,10 +90,12 @@ impl FileResolver {
         self.add_native();
         self
     }
+}
 
+impl<F: ModuleFs> FileResolver<F> {
     fn try_patterns(&self, path: &RelativePath) -> Option<RelativePathBuf> {
         if let Some(extension) = &path.extension() {
-            if !is_file(path) {
+            if !self.fs.is_file(path.as_str()) {
                 return None;
             }
             // check for known extensions
@@ -102,7 +117,7 @@ impl FileResolver {
                 .filter_map(|pattern| {
                     let name = pattern.replace("{}", path.file_name()?);
                     let file = path.with_file_name(name);
-                    if is_file(&file) {
+                    if self.fs.is_file(file.as_str()) {
                         Some(file)
                     } else {
                         None
@@ -118,11 +133,12 @@ impl Default for FileResolver {
         Self {
             paths: vec![],
             patterns: vec!["{}.js".into()],
+            fs: StdFs,
         }
     }
 }
 
-impl Resolver for FileResolver {
+impl<F: ModuleFs> Resolver for FileResolver<F> {
     fn resolve<'js>(&mut self, _ctx: Ctx<'js>, base: &str, name: &str) -> Result<String> {
         let path = if !name.starts_with('.') {
             self.paths
@@ -146,7 +162,3 @@ impl Resolver for FileResolver {
         Ok(path.to_string())
     }
 }
-
-fn is_file<P: AsRef<RelativePath>>(path: P) -> bool {
-    path.as_ref().to_path(".").is_file()
-}
@@ -0,0 +1,430 @@
+use super::{LoaderHolder, ModuleFs, ScriptLoader};
+use crate::{
+    Ctx, Loaded, Loader, Module, Mut, Object, ParallelSend, ParallelSync, Ref, Resolver, Result,
+    Script,
+};
+use std::collections::{HashMap, HashSet};
+
+/// The separator between module path and its version in module names
+const VERSION_SEP: &str = "?hot=";
+
+/// The specifier which is used by re-imported modules to import itself
+const SELF_SPECIFIER: &str = "<hot-reload>";
+
+/// The name of binding and `import.meta` property which holds the namespace of re-imported module
+const NAMESPACE_KEY: &str = "__hot_reload_namespace";
+
+/// The source of modules which is used to re-read changed modules
+trait Source: ParallelSend + ParallelSync + 'static {
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    fn stamp(&self, path: &str) -> Option<u64>;
+}
+
+impl<F> Source for ScriptLoader<F>
+where
+    F: ModuleFs + ParallelSend + ParallelSync + 'static,
+{
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.read_source(path)
+    }
+
+    fn stamp(&self, path: &str) -> Option<u64> {
+        self.fs().stamp(path)
+    }
+}
+
+/// Hot module reloading
+///
+/// This scope tracks the files which is loaded by the wrapped [`ScriptLoader`] and the dependencies
+/// between modules which is resolved by the wrapped resolver.
+/// The changes in files is detected by polling its stamps (see [`ModuleFs::stamp`]) using [`HotReload::poll`],
+/// so the files of any filesystem which is used by loader can be tracked.
+///
+/// Because QuickJS does not allow unloading of modules, the changed modules and its dependents is
+/// re-imported under versioned names like `lib/module.js?hot=1`.
+///
+/// ```no_run
+/// # use rquickjs::{Runtime, Context, HotReload, FileResolver, ScriptLoader, Object};
+/// let rt = Runtime::new().unwrap();
+/// let ctx = Context::full(&rt).unwrap();
+/// let hot = HotReload::new();
+/// rt.set_loader(
+///     hot.resolver(FileResolver::default()),
+///     hot.loader(ScriptLoader::default()),
+/// );
+/// ctx.with(|ctx| {
+///     ctx.compile("main", "import './app.js';").unwrap();
+///     // later, i.e. periodically in the event loop
+///     hot.poll(ctx, |_ctx, path, _namespace: Object| {
+///         println!("Module `{}` was reloaded", path);
+///         Ok(())
+///     })
+///     .unwrap();
+/// });
+/// ```
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Default, Clone)]
+pub struct HotReload<T = ()> {
+    data: Ref<Mut<HotReloadData>>,
+    inner: T,
+}
+
+impl HotReload {
+    /// Create new hot reloading scope
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create tracking resolver by wrapping other resolver
+    pub fn resolver<R: Resolver>(&self, resolver: R) -> HotReload<R> {
+        HotReload {
+            data: self.data.clone(),
+            inner: resolver,
+        }
+    }
+
+    /// Create tracking loader by wrapping script loader
+    ///
+    /// The clone of loader is kept by scope to re-read the changed modules.
+    pub fn loader<F>(&self, loader: ScriptLoader<F>) -> HotReload<ScriptLoader<F>>
+    where
+        F: ModuleFs + Clone + ParallelSend + ParallelSync + 'static,
+    {
+        self.data.lock().source = Some(Ref::new(loader.clone()));
+        HotReload {
+            data: self.data.clone(),
+            inner: loader,
+        }
+    }
+
+    /// Get paths of tracked files
+    pub fn files(&self) -> Vec<String> {
+        self.data.lock().files.keys().cloned().collect()
+    }
+
+    /// Check tracked files for changes and re-import changed modules with dependents
+    ///
+    /// The `reload` callback is called with the path and the namespace object of each re-imported module.
+    /// The dependencies is re-imported before its dependents.
+    /// Returns the number of re-imported modules.
+    ///
+    /// The modules is compiled and evaluated synchronously in the given context.
+    pub fn poll<'js, F>(&self, ctx: Ctx<'js>, mut reload: F) -> Result<usize>
+    where
+        F: FnMut(Ctx<'js>, &str, Object<'js>) -> Result<()>,
+    {
+        let (paths, source) = {
+            let mut data = self.data.lock();
+            (data.invalidate(), data.source.clone())
+        };
+        let source = match source {
+            Some(source) => source,
+            None => return Ok(0),
+        };
+
+        for path in &paths {
+            let name = self.data.lock().versioned(path.clone());
+            let namespace = reimport(ctx, &name, source.read(path)?)?;
+            reload(ctx, path, namespace)?;
+        }
+
+        Ok(paths.len())
+    }
+}
+
+/// Compile and evaluate the new version of module and returns its namespace
+///
+/// The module imports itself to expose its namespace via `import.meta`
+/// because QuickJS does not give access to the namespaces of modules.
+/// The import is appended to the end of source so the line numbers is kept.
+fn reimport<'js>(ctx: Ctx<'js>, name: &str, mut source: Vec<u8>) -> Result<Object<'js>> {
+    source.extend_from_slice(
+        format!(
+            "\n;import * as {key} from {:?}; import.meta.{key} = {key};",
+            SELF_SPECIFIER,
+            key = NAMESPACE_KEY
+        )
+        .as_bytes(),
+    );
+    let module = Module::new(ctx, name, source)?;
+    LoaderHolder::loaded(ctx, name, &module)?;
+    let meta = module.eval()?.meta::<Object>()?;
+    let namespace = meta.get(NAMESPACE_KEY)?;
+    meta.remove(NAMESPACE_KEY)?;
+    Ok(namespace)
+}
+
+#[derive(Default)]
+struct HotReloadData {
+    // { module_path: file_stamp }
+    files: HashMap<String, u64>,
+    // { module_path: { dependency_path } }
+    deps: HashMap<String, HashSet<String>>,
+    // { module_path: version }
+    versions: HashMap<String, usize>,
+    source: Option<Ref<dyn Source>>,
+}
+
+impl HotReloadData {
+    fn versioned(&self, path: String) -> String {
+        match self.versions.get(&path) {
+            Some(version) => format!("{}{}{}", path, VERSION_SEP, version),
+            None => path,
+        }
+    }
+
+    /// Find changed files, bump versions of changed modules with dependents and returns its paths ordered by dependencies
+    ///
+    /// The returned paths is not versioned.
+    fn invalidate(&mut self) -> Vec<String> {
+        let source = match &self.source {
+            Some(source) => source.clone(),
+            None => return Vec::new(),
+        };
+        let mut invalid = HashSet::new();
+        for (path, stamp) in &mut self.files {
+            if let Some(new_stamp) = source.stamp(path) {
+                if *stamp != new_stamp {
+                    *stamp = new_stamp;
+                    invalid.insert(path.clone());
+                }
+            }
+        }
+        if invalid.is_empty() {
+            return Vec::new();
+        }
+
+        // Collect dependents of changed modules
+        let mut stack = invalid.iter().cloned().collect::<Vec<_>>();
+        while let Some(path) = stack.pop() {
+            for (dependent, deps) in &self.deps {
+                if deps.contains(&path)
+                    && self.files.contains_key(dependent)
+                    && invalid.insert(dependent.clone())
+                {
+                    stack.push(dependent.clone());
+                }
+            }
+        }
+
+        for path in &invalid {
+            *self.versions.entry(path.clone()).or_default() += 1;
+        }
+
+        // Order modules so dependencies goes before dependents
+        let mut ordered = Vec::with_capacity(invalid.len());
+        let mut visited = HashSet::new();
+        let mut paths = invalid.iter().collect::<Vec<_>>();
+        paths.sort();
+        for path in paths {
+            self.visit(path, &invalid, &mut visited, &mut ordered);
+        }
+        ordered
+    }
+
+    fn visit(
+        &self,
+        path: &str,
+        invalid: &HashSet<String>,
+        visited: &mut HashSet<String>,
+        ordered: &mut Vec<String>,
+    ) {
+        if !visited.insert(path.into()) {
+            return;
+        }
+        if let Some(deps) = self.deps.get(path) {
+            let mut deps = deps
+                .iter()
+                .filter(|dep| invalid.contains(*dep))
+                .collect::<Vec<_>>();
+            deps.sort();
+            for dep in deps {
+                self.visit(dep, invalid, visited, ordered);
+            }
+        }
+        ordered.push(path.into());
+    }
+}
+
+/// Strip version from module name
+fn unversioned(name: &str) -> &str {
+    match name.rfind(VERSION_SEP) {
+        Some(pos) if name[pos + VERSION_SEP.len()..].parse::<usize>().is_ok() => &name[..pos],
+        _ => name,
+    }
+}
+
+impl<R> Resolver for HotReload<R>
+where
+    R: Resolver,
+{
+    fn resolve<'js>(&mut self, ctx: Ctx<'js>, base: &str, name: &str) -> Result<String> {
+        if name == SELF_SPECIFIER {
+            // The re-imported module imports itself
+            return Ok(base.into());
+        }
+        let base = unversioned(base);
+        let path = self.inner.resolve(ctx, base, name)?;
+        let mut data = self.data.lock();
+        data.deps
+            .entry(base.into())
+            .or_default()
+            .insert(path.clone());
+        Ok(data.versioned(path))
+    }
+}
+
+impl<F> Loader<Script> for HotReload<ScriptLoader<F>>
+where
+    F: ModuleFs,
+{
+    fn load<'js>(&mut self, ctx: Ctx<'js>, name: &str) -> Result<Module<'js, Loaded<Script>>> {
+        let path = unversioned(name);
+        let source = self.inner.read_source(path)?;
+        if let Some(stamp) = self.inner.fs().stamp(path) {
+            self.data.lock().files.insert(path.into(), stamp);
+        }
+        // The module should be named with version to be distinguished from the previous versions
+        Module::new(ctx, name, source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    struct TestResolver(PathBuf);
+
+    #[derive(Clone, Default)]
+    struct TestFs(Arc<Mutex<MemoryFs>>);
+
+    impl ModuleFs for TestFs {
+        fn exists(&self, path: &str) -> bool {
+            self.0.lock().unwrap().exists(path)
+        }
+
+        fn is_dir(&self, path: &str) -> bool {
+            self.0.lock().unwrap().is_dir(path)
+        }
+
+        fn read(&self, path: &str) -> Result<Vec<u8>> {
+            self.0.lock().unwrap().read(path)
+        }
+    }
+
+    impl Resolver for TestResolver {
+        fn resolve<'js>(&mut self, _ctx: Ctx<'js>, _base: &str, name: &str) -> Result<StdString> {
+            Ok(self.0.join(name).display().to_string())
+        }
+    }
+
+    #[test]
+    fn hot_reload() {
+        let dir = std::env::temp_dir().join("rquickjs_hot_reload_test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.js"), "export const n = 1;").unwrap();
+        fs::write(
+            dir.join("b.js"),
+            "import { n } from 'a.js'; export const m = n;",
+        )
+        .unwrap();
+        fs::write(dir.join("c.js"), "export const k = 1;").unwrap();
+
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let hot = HotReload::new();
+        rt.set_loader(
+            hot.resolver(TestResolver(dir.clone())),
+            hot.loader(ScriptLoader::default()),
+        );
+        ctx.with(|ctx| {
+            ctx.compile("main", "import 'b.js'; import 'c.js';")
+                .unwrap();
+            assert_eq!(hot.files().len(), 3);
+            assert_eq!(hot.poll(ctx, |_, _, _| Ok(())).unwrap(), 0);
+
+            fs::write(dir.join("a.js"), "export const n = 22;").unwrap();
+            let mut reloaded = Vec::new();
+            let count = hot
+                .poll(ctx, |_, path, namespace| {
+                    reloaded.push((path.to_string(), namespace));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(count, 2);
+            assert!(reloaded[0].0.ends_with("a.js"));
+            assert_eq!(reloaded[0].1.get::<_, i32>("n").unwrap(), 22);
+            assert!(reloaded[1].0.ends_with("b.js"));
+            assert_eq!(reloaded[1].1.get::<_, i32>("m").unwrap(), 22);
+
+            // the versions is bumped again on the next change
+            fs::write(dir.join("c.js"), "export const k = 333;").unwrap();
+            let mut reloaded = Vec::new();
+            let count = hot
+                .poll(ctx, |_, path, namespace| {
+                    reloaded.push((path.to_string(), namespace));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(count, 1);
+            assert!(reloaded[0].0.ends_with("c.js"));
+            assert_eq!(reloaded[0].1.get::<_, i32>("k").unwrap(), 333);
+        })
+    }
+    #[test]
+    fn hot_reload_module_fs() {
+        let fs = TestFs::default();
+        fs.0.lock()
+            .unwrap()
+            .add_file("a.js", "export const n = 1;")
+            .add_file("b.js", "import { n } from 'a.js'; export const m = n;");
+
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let hot = HotReload::new();
+        rt.set_loader(
+            hot.resolver(FileResolver::default().with_fs(fs.clone())),
+            hot.loader(ScriptLoader::default().with_fs(fs.clone())),
+        );
+        ctx.with(|ctx| {
+            ctx.compile("main", "import 'b.js';").unwrap();
+            assert_eq!(hot.files().len(), 2);
+
+            // the unrelated pending jobs should not be executed by reloading
+            let _: () = ctx
+                .eval("Promise.resolve().then(() => { globalThis.ran = true; })")
+                .unwrap();
+            fs.0.lock().unwrap().add_file(
+                "a.js",
+                "export const n = import.meta.url.length > 0 ? 22 : 0;",
+            );
+            let mut reloaded = Vec::new();
+            let count = hot
+                .poll(ctx, |_, path, namespace| {
+                    reloaded.push((path.to_string(), namespace));
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(count, 2);
+            assert_eq!(reloaded[0].0, "a.js");
+            assert_eq!(reloaded[0].1.get::<_, i32>("n").unwrap(), 22);
+            assert_eq!(reloaded[1].0, "b.js");
+            assert_eq!(reloaded[1].1.get::<_, i32>("m").unwrap(), 22);
+            assert!(!reloaded[0]
+                .1
+                .contains_key("__hot_reload_namespace")
+                .unwrap());
+            assert!(ctx
+                .globals()
+                .get::<_, Option<bool>>("ran")
+                .unwrap()
+                .is_none());
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use super::{check_extensions, ModuleFs, StdFs};
+use crate::{Ctx, Error, Loaded, Loader, Module, Native, Result, Value};
+
+/// The JSON module loader
+///
+/// This loader turns JSON files into modules with single `default` export which holds parsed data.
+/// Note that [`FileResolver`](crate::FileResolver) should be configured to find JSON files, i.e. using `with_pattern("{}.json")`.
+///
+/// This loader can be used as the nested backing loader in user-defined loaders.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug)]
+pub struct JsonLoader<F = StdFs> {
+    extensions: Vec<String>,
+    fs: F,
+}
+
+impl<F> JsonLoader<F> {
+    /// Use the specified filesystem to read modules
+    pub fn with_fs<G: ModuleFs>(self, fs: G) -> JsonLoader<G> {
+        JsonLoader {
+            extensions: self.extensions,
+            fs,
+        }
+    }
+
+    /// Add JSON file extension
+    pub fn add_extension<X: Into<String>>(&mut self, extension: X) -> &mut Self {
+        self.extensions.push(extension.into());
+        self
+    }
+
+    /// Add JSON file extension
+    #[must_use]
+    pub fn with_extension<X: Into<String>>(mut self, extension: X) -> Self {
+        self.add_extension(extension);
+        self
+    }
+}
+
+impl Default for JsonLoader {
+    fn default() -> Self {
+        Self {
+            extensions: vec!["json".into()],
+            fs: StdFs,
+        }
+    }
+}
+
+impl<F: ModuleFs> Loader<Native> for JsonLoader<F> {
+    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Native>>> {
+        if !check_extensions(path, &self.extensions) {
+            return Err(Error::new_loading(path));
+        }
+
+        let source = self.fs.read(path)?;
+        let data: Value = ctx.json_parse(source)?;
+        Module::new_default(ctx, path, data)
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{Error, Result};
+use relative_path::RelativePath;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+};
+
+#[cfg(any(feature = "archive-tar", feature = "archive-zip"))]
+use std::io::Read;
+
+/// The filesystem interface for module resolvers and loaders
+///
+/// The paths is the module names in form of relative paths, i.e. `"lib/module.js"`.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+pub trait ModuleFs {
+    /// Check whether the path exists
+    fn exists(&self, path: &str) -> bool;
+
+    /// Check whether the path is a directory
+    fn is_dir(&self, path: &str) -> bool;
+
+    /// Read the contents of file
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// Check whether the path is a regular file
+    fn is_file(&self, path: &str) -> bool {
+        self.exists(path) && !self.is_dir(path)
+    }
+
+    /// Get the stamp of file which changes when the file is changed
+    ///
+    /// This is used to detect changes in modules (see [`HotReload`](crate::HotReload)).
+    /// The default implementation hashes the contents of file.
+    fn stamp(&self, path: &str) -> Option<u64> {
+        let data = self.read(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+macro_rules! module_fs_impls {
+    ($($(#[$meta:meta])* $type:ty,)*) => {
+        $(
+            $(#[$meta])*
+            impl<T> ModuleFs for $type
+            where
+                T: ModuleFs + ?Sized,
+            {
+                fn exists(&self, path: &str) -> bool {
+                    T::exists(self, path)
+                }
+
+                fn is_dir(&self, path: &str) -> bool {
+                    T::is_dir(self, path)
+                }
+
+                fn read(&self, path: &str) -> Result<Vec<u8>> {
+                    T::read(self, path)
+                }
+
+                fn is_file(&self, path: &str) -> bool {
+                    T::is_file(self, path)
+                }
+
+                fn stamp(&self, path: &str) -> Option<u64> {
+                    T::stamp(self, path)
+                }
+            }
+        )*
+    };
+}
+
+module_fs_impls! {
+    &T,
+    Box<T>,
+    /// The filesystem can be shared between resolver and loader using reference counting
+    Rc<T>,
+    Arc<T>,
+}
+
+/// The real filesystem
+///
+/// The relative paths is treated as relative to the current working directory.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdFs;
+
+impl ModuleFs for StdFs {
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        Path::new(path).is_dir()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        Path::new(path).is_file()
+    }
+
+    fn stamp(&self, path: &str) -> Option<u64> {
+        let meta = std::fs::metadata(path).ok()?;
+        let mut hasher = DefaultHasher::new();
+        meta.modified().ok()?.hash(&mut hasher);
+        meta.len().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+/// The in-memory filesystem
+///
+/// The directories is implied by the paths of added files.
+///
+/// ```
+/// # use rquickjs::{MemoryFs, FileResolver, ScriptLoader};
+/// # use std::rc::Rc;
+/// let fs = Rc::new(
+///     MemoryFs::default()
+///         .with_file("main.js", "import { n } from './lib/n.js';")
+///         .with_file("lib/n.js", "export const n = 1;"),
+/// );
+/// let resolver = FileResolver::default().with_fs(fs.clone());
+/// let loader = ScriptLoader::default().with_fs(fs);
+/// ```
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFs {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryFs {
+    /// Add file
+    pub fn add_file<P: AsRef<str>, D: Into<Vec<u8>>>(&mut self, path: P, data: D) -> &mut Self {
+        self.files.insert(normalize(path.as_ref()), data.into());
+        self
+    }
+
+    /// Add file
+    #[must_use]
+    pub fn with_file<P: AsRef<str>, D: Into<Vec<u8>>>(mut self, path: P, data: D) -> Self {
+        self.add_file(path, data);
+        self
+    }
+
+    /// Remove file
+    pub fn remove_file<P: AsRef<str>>(&mut self, path: P) -> Option<Vec<u8>> {
+        self.files.remove(&normalize(path.as_ref()))
+    }
+}
+
+impl ModuleFs for MemoryFs {
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize(path)) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        is_dir(self.files.keys(), path)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        read(&self.files, path).map(|data| data.to_vec())
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+}
+
+/// The read-only filesystem backed by an archive
+///
+/// The archive is unpacked into memory on creation.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Clone)]
+pub struct ArchiveFs {
+    files: HashMap<String, Box<[u8]>>,
+}
+
+impl ArchiveFs {
+    /// Create filesystem from the entries of a tar archive
+    #[cfg(feature = "archive-tar")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "archive-tar")))]
+    pub fn from_tar<R: Read>(reader: R) -> Result<Self> {
+        let mut archive = tar::Archive::new(reader);
+        let mut files = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?;
+            let path = path
+                .to_str()
+                .ok_or_else(|| {
+                    Error::new_loading_message(path.display().to_string(), "Invalid path")
+                })?
+                .to_string();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            files.insert(normalize(&path), data.into());
+        }
+        Ok(Self { files })
+    }
+
+    /// Create filesystem from the entries of a zip archive
+    #[cfg(feature = "archive-zip")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "archive-zip")))]
+    pub fn from_zip<R: Read + std::io::Seek>(reader: R) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+        let mut files = HashMap::new();
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index).map_err(zip_error)?;
+            if !entry.is_file() {
+                continue;
+            }
+            let path = normalize(entry.name());
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            files.insert(path, data.into());
+        }
+        Ok(Self { files })
+    }
+}
+
+impl ModuleFs for ArchiveFs {
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize(path)) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        is_dir(self.files.keys(), path)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        read(&self.files, path).map(|data| data.to_vec())
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+}
+
+#[cfg(feature = "archive-zip")]
+fn zip_error(error: zip::result::ZipError) -> Error {
+    match error {
+        zip::result::ZipError::Io(error) => error.into(),
+        error => Error::new_loading_message("<archive>", error.to_string()),
+    }
+}
+
+fn normalize(path: &str) -> String {
+    RelativePath::new(path).normalize().into_string()
+}
+
+fn is_dir<'a, I: Iterator<Item = &'a String>>(mut files: I, path: &str) -> bool {
+    let path = normalize(path);
+    if path.is_empty() {
+        // the root always exists
+        return true;
+    }
+    files.any(|file| {
+        file.len() > path.len() && file.starts_with(&path) && file.as_bytes()[path.len()] == b'/'
+    })
+}
+
+fn read<'a, D: AsRef<[u8]>>(files: &'a HashMap<String, D>, path: &str) -> Result<&'a [u8]> {
+    files
+        .get(&normalize(path))
+        .map(|data| data.as_ref())
+        .ok_or_else(|| Error::new_loading_message(path, "File not found"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_fs() {
+        let fs = MemoryFs::default()
+            .with_file("./lib/a.js", "a")
+            .with_file("lib/sub/b.js", "b");
+
+        assert!(fs.is_file("lib/a.js"));
+        assert!(fs.is_file("lib/../lib/a.js"));
+        assert!(fs.is_dir("lib"));
+        assert!(fs.is_dir("lib/sub"));
+        assert!(!fs.is_dir("li"));
+        assert!(fs.exists("lib"));
+        assert!(!fs.exists("lib/c.js"));
+        assert_eq!(fs.read("lib/sub/b.js").unwrap(), b"b");
+        assert!(fs.read("lib/c.js").is_err());
+    }
+
+    #[cfg(feature = "archive-tar")]
+    #[test]
+    fn tar_fs() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in &[("lib/a.js", "a"), ("./lib/sub/b.js", "b")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, data.as_bytes())
+                .unwrap();
+        }
+        let archive = builder.into_inner().unwrap();
+
+        let fs = ArchiveFs::from_tar(archive.as_slice()).unwrap();
+        assert!(fs.is_file("lib/a.js"));
+        assert!(fs.is_dir("lib/sub"));
+        assert_eq!(fs.read("lib/sub/b.js").unwrap(), b"b");
+        assert!(fs.read("lib/c.js").is_err());
+    }
+
+    #[cfg(feature = "archive-zip")]
+    #[test]
+    fn zip_fs() {
+        use std::io::{Cursor, Write};
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_directory("lib/sub/", Default::default())
+            .unwrap();
+        for (path, data) in &[("lib/a.js", "a"), ("lib/sub/b.js", "b")] {
+            writer.start_file(*path, Default::default()).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        let archive = writer.finish().unwrap().into_inner();
+
+        let fs = ArchiveFs::from_zip(Cursor::new(archive)).unwrap();
+        assert!(fs.is_file("lib/a.js"));
+        assert!(fs.is_dir("lib/sub"));
+        assert!(!fs.is_file("lib/sub"));
+        assert_eq!(fs.read("lib/sub/b.js").unwrap(), b"b");
+        assert!(ArchiveFs::from_zip(Cursor::new(b"not a zip".to_vec())).is_err());
+    }
+}
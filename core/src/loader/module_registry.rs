@@ -0,0 +1,154 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter::{ExactSizeIterator, FusedIterator},
+    slice::Iter as SliceIter,
+};
+
+/// The state of module in the registry
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModuleState {
+    /// The module name was resolved but the module is not loaded yet
+    Resolved,
+    /// The module was loaded or compiled but not evaluated yet
+    Loaded,
+    /// The module was evaluated
+    Evaluated,
+    /// The loading of module was failed
+    Failed,
+}
+
+/// The information about module in the registry
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    name: String,
+    state: ModuleState,
+    // [ (specifier, resolved_name) ]
+    requests: Vec<(String, String)>,
+}
+
+impl ModuleInfo {
+    fn new(name: String, state: ModuleState) -> Self {
+        Self {
+            name,
+            state,
+            requests: Vec::new(),
+        }
+    }
+
+    /// The resolved name of module
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The current state of module
+    pub fn state(&self) -> ModuleState {
+        self.state
+    }
+
+    /// The requested modules
+    ///
+    /// Each item is a tuple of requested _specifier_ (`&str`) and resolved module _name_ (`&str`).
+    pub fn requested_modules(&self) -> RequestedModulesIter {
+        RequestedModulesIter(self.requests.iter())
+    }
+
+    /// The resolved names of dependencies
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.requests.iter().map(|(_, name)| name.as_str())
+    }
+}
+
+/// An iterator over requested modules
+///
+/// Each item is a tuple of requested specifier and resolved module name.
+pub struct RequestedModulesIter<'i>(SliceIter<'i, (String, String)>);
+
+impl<'i> Iterator for RequestedModulesIter<'i> {
+    type Item = (&'i str, &'i str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|(specifier, name)| (specifier.as_str(), name.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'i> ExactSizeIterator for RequestedModulesIter<'i> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'i> FusedIterator for RequestedModulesIter<'i> {}
+
+/// The registry of modules of single context
+///
+/// The registry is filled by module loader hooks, so only the modules which imports or is imported by other modules is tracked.
+#[derive(Debug, Default)]
+pub(crate) struct ModuleRegistry {
+    modules: Vec<ModuleInfo>,
+    // { module_name: index }
+    indices: HashMap<String, usize>,
+}
+
+impl ModuleRegistry {
+    fn index(&mut self, name: &str, state: ModuleState) -> usize {
+        match self.indices.get(name) {
+            Some(index) => *index,
+            None => {
+                let index = self.modules.len();
+                self.modules.push(ModuleInfo::new(name.into(), state));
+                self.indices.insert(name.into(), index);
+                index
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ModuleInfo> {
+        self.indices.get(name).map(|index| &self.modules[*index])
+    }
+
+    pub fn iter(&self) -> SliceIter<ModuleInfo> {
+        self.modules.iter()
+    }
+
+    /// Register resolved module request
+    pub fn add_request(&mut self, base: &str, specifier: &str, name: &str) {
+        // The importing module is being compiled when it is not registered yet
+        let index = self.index(base, ModuleState::Loaded);
+        let requests = &mut self.modules[index].requests;
+        if !requests.iter().any(|(other, _)| other == specifier) {
+            requests.push((specifier.into(), name.into()));
+        }
+        self.index(name, ModuleState::Resolved);
+    }
+
+    pub fn set_state(&mut self, name: &str, state: ModuleState) {
+        let index = self.index(name, state);
+        self.modules[index].state = state;
+    }
+
+    /// Mark module with dependencies as evaluated
+    pub fn set_evaluated(&mut self, name: &str) {
+        let index = self.index(name, ModuleState::Evaluated);
+        let mut visited = HashSet::new();
+        visited.insert(index);
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            self.modules[index].state = ModuleState::Evaluated;
+            for (_, name) in &self.modules[index].requests {
+                if let Some(index) = self.indices.get(name) {
+                    if visited.insert(*index) {
+                        stack.push(*index);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,25 @@
-use super::check_extensions;
+use super::{check_extensions, ModuleFs, StdFs};
 use crate::{Ctx, Error, Loaded, Loader, Module, Result, Script};
 
 /// The script module loader
 ///
 /// This loader can be used as the nested backing loader in user-defined loaders.
 #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
-#[derive(Debug)]
-pub struct ScriptLoader {
+#[derive(Debug, Clone)]
+pub struct ScriptLoader<F = StdFs> {
     extensions: Vec<String>,
+    fs: F,
 }
 
-impl ScriptLoader {
+impl<F> ScriptLoader<F> {
+    /// Use the specified filesystem to read modules
+    pub fn with_fs<G: ModuleFs>(self, fs: G) -> ScriptLoader<G> {
+        ScriptLoader {
+            extensions: self.extensions,
+            fs,
+        }
+    }
+
     /// Add script file extension
     pub fn add_extension<X: Into<String>>(&mut self, extension: X) -> &mut Self {
         self.extensions.push(extension.into());
@@ -29,17 +38,30 @@ impl Default for ScriptLoader {
     fn default() -> Self {
         Self {
             extensions: vec!["js".into()],
+            fs: StdFs,
         }
     }
 }
 
-impl Loader<Script> for ScriptLoader {
-    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Script>>> {
+impl<F: ModuleFs> ScriptLoader<F> {
+    /// Read the source of script module
+    pub(crate) fn read_source(&self, path: &str) -> Result<Vec<u8>> {
         if !check_extensions(path, &self.extensions) {
             return Err(Error::new_loading(path));
         }
 
-        let source: Vec<_> = std::fs::read(path)?;
+        self.fs.read(path)
+    }
+
+    /// The filesystem which is used to read modules
+    pub(crate) fn fs(&self) -> &F {
+        &self.fs
+    }
+}
+
+impl<F: ModuleFs> Loader<Script> for ScriptLoader<F> {
+    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Script>>> {
+        let source = self.read_source(path)?;
         Module::new(ctx, path, source)
     }
 }
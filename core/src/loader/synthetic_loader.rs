@@ -0,0 +1,160 @@
+use crate::{Ctx, Error, IntoJs, Loaded, Loader, Module, Native, Object, Result};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+#[cfg(feature = "array-buffer")]
+use crate::ArrayBuffer;
+
+type ExportsInitFn = dyn for<'js> Fn(Ctx<'js>) -> Result<Object<'js>>;
+
+struct ExportsInit(Box<ExportsInitFn>);
+
+impl Debug for ExportsInit {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        "<exports>".fmt(f)
+    }
+}
+
+/// The synthetic module loader
+///
+/// This loader creates modules from the data provided by host, i.e. the maps of values or text and binary assets.
+/// The properties of exports object becomes the named exports of module.
+///
+/// ```
+/// # use rquickjs::SyntheticLoader;
+/// # use std::collections::HashMap;
+/// let mut exports = HashMap::new();
+/// exports.insert("answer", 42);
+///
+/// let loader = SyntheticLoader::default()
+///     .with_module("config", exports)
+///     .with_text("readme", "Hello!");
+/// ```
+///
+/// The exports is created on each loading so the same module can be imported by many contexts.
+///
+/// This loader can be used as the nested backing loader in user-defined loaders.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+#[derive(Debug, Default)]
+pub struct SyntheticLoader {
+    modules: HashMap<String, ExportsInit>,
+}
+
+impl SyntheticLoader {
+    /// Add module with exports produced by function
+    ///
+    /// The function should return an object which properties will be exported.
+    /// It is called each time the module is loaded into a context.
+    pub fn add_module_fn<N, F>(&mut self, name: N, init: F) -> &mut Self
+    where
+        N: Into<String>,
+        F: for<'js> Fn(Ctx<'js>) -> Result<Object<'js>> + 'static,
+    {
+        self.modules
+            .insert(name.into(), ExportsInit(Box::new(init)));
+        self
+    }
+
+    /// Add module with exports produced by function
+    #[must_use]
+    pub fn with_module_fn<N, F>(mut self, name: N, init: F) -> Self
+    where
+        N: Into<String>,
+        F: for<'js> Fn(Ctx<'js>) -> Result<Object<'js>> + 'static,
+    {
+        self.add_module_fn(name, init);
+        self
+    }
+
+    /// Add module with exports from map-like value
+    ///
+    /// The value should be converted to an object, i.e. it may be [`HashMap`] or [`BTreeMap`](std::collections::BTreeMap).
+    pub fn add_module<N, E>(&mut self, name: N, exports: E) -> &mut Self
+    where
+        N: Into<String>,
+        E: for<'js> IntoJs<'js> + Clone + 'static,
+    {
+        self.add_module_fn(name, move |ctx| {
+            Object::from_value(exports.clone().into_js(ctx)?)
+        })
+    }
+
+    /// Add module with exports from map-like value
+    #[must_use]
+    pub fn with_module<N, E>(mut self, name: N, exports: E) -> Self
+    where
+        N: Into<String>,
+        E: for<'js> IntoJs<'js> + Clone + 'static,
+    {
+        self.add_module(name, exports);
+        self
+    }
+
+    /// Add module with the single `default` export
+    pub fn add_default<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<String>,
+        V: for<'js> IntoJs<'js> + Clone + 'static,
+    {
+        self.add_module_fn(name, move |ctx| {
+            let exports = Object::new(ctx)?;
+            exports.set("default", value.clone())?;
+            Ok(exports)
+        })
+    }
+
+    /// Add module with the single `default` export
+    #[must_use]
+    pub fn with_default<N, V>(mut self, name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: for<'js> IntoJs<'js> + Clone + 'static,
+    {
+        self.add_default(name, value);
+        self
+    }
+
+    /// Add text asset module which exports string as `default`
+    pub fn add_text<N: Into<String>, S: Into<String>>(&mut self, name: N, text: S) -> &mut Self {
+        self.add_default(name, text.into())
+    }
+
+    /// Add text asset module which exports string as `default`
+    #[must_use]
+    pub fn with_text<N: Into<String>, S: Into<String>>(mut self, name: N, text: S) -> Self {
+        self.add_text(name, text);
+        self
+    }
+
+    /// Add binary asset module which exports `ArrayBuffer` as `default`
+    #[cfg(feature = "array-buffer")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "array-buffer")))]
+    pub fn add_bytes<N: Into<String>, B: Into<Vec<u8>>>(&mut self, name: N, bytes: B) -> &mut Self {
+        let bytes = bytes.into();
+        self.add_module_fn(name, move |ctx| {
+            let exports = Object::new(ctx)?;
+            exports.set("default", ArrayBuffer::new(ctx, bytes.clone())?)?;
+            Ok(exports)
+        })
+    }
+
+    /// Add binary asset module which exports `ArrayBuffer` as `default`
+    #[cfg(feature = "array-buffer")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "array-buffer")))]
+    #[must_use]
+    pub fn with_bytes<N: Into<String>, B: Into<Vec<u8>>>(mut self, name: N, bytes: B) -> Self {
+        self.add_bytes(name, bytes);
+        self
+    }
+}
+
+impl Loader<Native> for SyntheticLoader {
+    fn load<'js>(&mut self, ctx: Ctx<'js>, path: &str) -> Result<Module<'js, Loaded<Native>>> {
+        match self.modules.get(path) {
+            Some(init) => Module::new_object(ctx, path, (init.0)(ctx)?),
+            _ => Err(Error::new_loading(path)),
+        }
+    }
+}
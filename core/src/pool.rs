@@ -0,0 +1,428 @@
+//! The pool of runtimes
+//!
+//! The [`RuntimePool`] keeps the runtimes with pre-initialized contexts ready to use.
+//! The runtime is checked out from the pool and returned back automatically when the [`PooledRuntime`] is dropped.
+//!
+//! On returning the runtime is reset: the garbage collection is run and the memory limit is reapplied.
+//! Then the health check is run using the [`MemoryUsage`] of runtime and
+//! the unhealthy runtime is dropped and recreated using the init function on the next checkout.
+//! The runtime with pending jobs (i.e. unsettled promise reactions) is always treated as unhealthy
+//! so the jobs of one user cannot be run by the next one.
+//!
+//! With the `"parallel"` feature the pool can be shared between threads.
+//! With the `"futures"` feature the runtimes can be checked out asynchronously using any supported executor.
+//!
+//! ```
+//! # use rquickjs::{Context, pool::RuntimePool};
+//! let pool = RuntimePool::builder()
+//!     .size(2)
+//!     .memory_limit(16 << 20)
+//!     .health_check(|usage| usage.memory_used_size < 8 << 20)
+//!     .build(|rt| {
+//!         let ctx = Context::full(rt)?;
+//!         ctx.with(|ctx| ctx.eval::<(), _>("globalThis.answer = 42"))?;
+//!         Ok(ctx)
+//!     })
+//!     .unwrap();
+//!
+//! let runtime = pool.checkout().unwrap();
+//! let answer: i32 = runtime.context().with(|ctx| ctx.eval("answer").unwrap());
+//! assert_eq!(answer, 42);
+//! ```
+
+use crate::{Context, Error, MemoryUsage, ParallelSend, ParallelSync, Ref, Result, Runtime};
+use flume::{Receiver, Sender};
+
+/// The initializer of pooled runtimes
+trait PoolInit: ParallelSend + ParallelSync + 'static {
+    fn init(&self, runtime: &Runtime) -> Result<Context>;
+}
+
+impl<F> PoolInit for F
+where
+    F: Fn(&Runtime) -> Result<Context> + ParallelSend + ParallelSync + 'static,
+{
+    fn init(&self, runtime: &Runtime) -> Result<Context> {
+        self(runtime)
+    }
+}
+
+/// The health check of pooled runtimes
+trait PoolCheck: ParallelSend + ParallelSync + 'static {
+    fn check(&self, usage: &MemoryUsage) -> bool;
+}
+
+impl<F> PoolCheck for F
+where
+    F: Fn(&MemoryUsage) -> bool + ParallelSend + ParallelSync + 'static,
+{
+    fn check(&self, usage: &MemoryUsage) -> bool {
+        self(usage)
+    }
+}
+
+/// The builder of runtime pool
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "pool")))]
+pub struct RuntimePoolBuilder {
+    size: usize,
+    memory_limit: Option<usize>,
+    health_check: Option<Box<dyn PoolCheck>>,
+}
+
+impl Default for RuntimePoolBuilder {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            memory_limit: None,
+            health_check: None,
+        }
+    }
+}
+
+impl RuntimePoolBuilder {
+    /// Set the number of runtimes in pool (1 by default)
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size.max(1);
+        self
+    }
+
+    /// Set the memory limit which is applied to runtimes on creating and returning
+    pub fn memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /// Set the health check of returned runtimes
+    ///
+    /// The runtime is recreated when the check returns `false`.
+    /// The check is not run for the runtimes with pending jobs which is always recreated.
+    pub fn health_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&MemoryUsage) -> bool + ParallelSend + ParallelSync + 'static,
+    {
+        self.health_check = Some(Box::new(check));
+        self
+    }
+
+    /// Build pool using the function which creates context for each runtime
+    ///
+    /// All runtimes is created here so the initialization errors is returned early.
+    pub fn build<F>(self, init: F) -> Result<RuntimePool>
+    where
+        F: Fn(&Runtime) -> Result<Context> + ParallelSend + ParallelSync + 'static,
+    {
+        let (sender, receiver) = flume::unbounded();
+        let inner = Inner {
+            sender,
+            receiver,
+            init: Box::new(init),
+            memory_limit: self.memory_limit,
+            health_check: self.health_check,
+            size: self.size,
+        };
+        for _ in 0..self.size {
+            let slot = inner.create()?;
+            inner.put(Some(slot));
+        }
+        Ok(RuntimePool {
+            inner: Ref::new(inner),
+        })
+    }
+}
+
+type Slot = Option<(Runtime, Context)>;
+
+struct Inner {
+    sender: Sender<Slot>,
+    receiver: Receiver<Slot>,
+    init: Box<dyn PoolInit>,
+    memory_limit: Option<usize>,
+    health_check: Option<Box<dyn PoolCheck>>,
+    size: usize,
+}
+
+impl Inner {
+    fn create(&self) -> Result<(Runtime, Context)> {
+        let runtime = Runtime::new()?;
+        if let Some(limit) = self.memory_limit {
+            runtime.set_memory_limit(limit);
+        }
+        let context = self.init.init(&runtime)?;
+        Ok((runtime, context))
+    }
+
+    fn put(&self, slot: Slot) {
+        // the receiver is owned by pool so the channel cannot be disconnected
+        let _ = self.sender.send(slot);
+    }
+
+    /// Take runtime from slot or create new one when the slot is empty
+    fn take(&self, slot: Slot) -> Result<(Runtime, Context)> {
+        match slot {
+            Some(slot) => Ok(slot),
+            None => {
+                let result = self.create();
+                if result.is_err() {
+                    // keep the slot for the next checkout
+                    self.put(None);
+                }
+                result
+            }
+        }
+    }
+
+    fn reset(&self, runtime: &Runtime) -> bool {
+        if runtime.is_job_pending() {
+            return false;
+        }
+        runtime.run_gc();
+        if let Some(limit) = self.memory_limit {
+            runtime.set_memory_limit(limit);
+        }
+        match &self.health_check {
+            Some(check) => check.check(&runtime.memory_usage()),
+            None => true,
+        }
+    }
+}
+
+/// The pool of runtimes with pre-initialized contexts
+///
+/// The clones of pool shares the same runtimes.
+/// See [module docs](self) for details.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "pool")))]
+#[derive(Clone)]
+pub struct RuntimePool {
+    inner: Ref<Inner>,
+}
+
+impl RuntimePool {
+    /// Create builder of pool
+    pub fn builder() -> RuntimePoolBuilder {
+        RuntimePoolBuilder::default()
+    }
+
+    /// Get the number of runtimes in pool
+    pub fn size(&self) -> usize {
+        self.inner.size
+    }
+
+    /// Get the number of runtimes which is available for checkout
+    pub fn available(&self) -> usize {
+        self.inner.receiver.len()
+    }
+
+    /// Checkout runtime, blocks until the runtime is available
+    pub fn checkout(&self) -> Result<PooledRuntime> {
+        let slot = self.inner.receiver.recv().map_err(|_| Error::Unknown)?;
+        self.pooled(slot)
+    }
+
+    /// Checkout runtime when it is available
+    pub fn try_checkout(&self) -> Result<Option<PooledRuntime>> {
+        match self.inner.receiver.try_recv() {
+            Ok(slot) => self.pooled(slot).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Checkout runtime asynchronously
+    #[cfg(feature = "futures")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "futures")))]
+    pub async fn checkout_async(&self) -> Result<PooledRuntime> {
+        let slot = self
+            .inner
+            .receiver
+            .recv_async()
+            .await
+            .map_err(|_| Error::Unknown)?;
+        self.pooled(slot)
+    }
+
+    fn pooled(&self, slot: Slot) -> Result<PooledRuntime> {
+        let (runtime, context) = self.inner.take(slot)?;
+        Ok(PooledRuntime {
+            slot: Some((runtime, context)),
+            pool: self.inner.clone(),
+        })
+    }
+}
+
+/// The runtime which is checked out from [`RuntimePool`]
+///
+/// The runtime is returned to pool when it is dropped.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "pool")))]
+pub struct PooledRuntime {
+    slot: Slot,
+    pool: Ref<Inner>,
+}
+
+impl PooledRuntime {
+    /// Get the runtime
+    pub fn runtime(&self) -> &Runtime {
+        &self.slot.as_ref().unwrap().0
+    }
+
+    /// Get the pre-initialized context
+    pub fn context(&self) -> &Context {
+        &self.slot.as_ref().unwrap().1
+    }
+
+    /// Drop the runtime so it will be recreated on next checkout
+    pub fn discard(mut self) {
+        self.slot = None;
+    }
+}
+
+impl Drop for PooledRuntime {
+    fn drop(&mut self) {
+        let slot = self
+            .slot
+            .take()
+            .filter(|(runtime, _)| self.pool.reset(runtime));
+        self.pool.put(slot);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    fn pool(created: Arc<AtomicUsize>) -> RuntimePool {
+        RuntimePool::builder()
+            .size(2)
+            .memory_limit(32 << 20)
+            .health_check(|usage| usage.obj_count < 10_000)
+            .build(move |rt| {
+                created.fetch_add(1, Ordering::SeqCst);
+                let ctx = Context::full(rt)?;
+                ctx.with(|ctx| ctx.eval::<(), _>("globalThis.objects = []"))?;
+                Ok(ctx)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn checkout() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let pool = pool(created.clone());
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.available(), 2);
+
+        let first = pool.checkout().unwrap();
+        let second = pool.checkout().unwrap();
+        assert_eq!(pool.available(), 0);
+        assert!(pool.try_checkout().unwrap().is_none());
+
+        let _: () = first
+            .context()
+            .with(|ctx| ctx.eval("objects.push({})"))
+            .unwrap();
+        // the memory limit is reapplied on returning
+        first.runtime().set_memory_limit(1);
+        drop(first);
+        let first = pool.try_checkout().unwrap().unwrap();
+        let len: usize = first
+            .context()
+            .with(|ctx| ctx.eval("objects.push({})"))
+            .unwrap();
+        assert_eq!(len, 2);
+
+        // the discarded runtime is recreated on checkout
+        second.discard();
+        drop(first);
+        let _first = pool.checkout().unwrap();
+        let _second = pool.checkout().unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn health_check() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let pool = pool(created.clone());
+        let runtime = pool.checkout().unwrap();
+        let _: () = runtime
+            .context()
+            .with(|ctx| ctx.eval("for (let i = 0; i < 20000; i++) objects.push({})"))
+            .unwrap();
+        drop(runtime);
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+
+        let runtime = pool.checkout().unwrap();
+        let runtime2 = pool.checkout().unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+        for runtime in &[runtime, runtime2] {
+            let len: usize = runtime
+                .context()
+                .with(|ctx| ctx.eval("objects.length"))
+                .unwrap();
+            assert_eq!(len, 0);
+        }
+    }
+
+    #[test]
+    fn pending_jobs() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let pool = pool(created.clone());
+        let runtime = pool.checkout().unwrap();
+        let _: () = runtime
+            .context()
+            .with(|ctx| ctx.eval("void Promise.resolve().then(() => objects.push({}))"))
+            .unwrap();
+        assert!(runtime.runtime().is_job_pending());
+        drop(runtime);
+
+        let runtime = pool.checkout().unwrap();
+        let runtime2 = pool.checkout().unwrap();
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+        for runtime in &[runtime, runtime2] {
+            assert!(!runtime.runtime().is_job_pending());
+            let len: usize = runtime
+                .context()
+                .with(|ctx| ctx.eval("objects.length"))
+                .unwrap();
+            assert_eq!(len, 0);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn threads() {
+        let pool = pool(Arc::new(AtomicUsize::new(0)));
+        let handles = (0..4)
+            .map(|index| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    let runtime = pool.checkout().unwrap();
+                    runtime
+                        .context()
+                        .with(|ctx| ctx.eval::<i32, _>(format!("{} * 2", index)))
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        let sum: i32 = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum();
+        assert_eq!(sum, 12);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[async_std::test]
+    async fn checkout_async() {
+        let pool = pool(Arc::new(AtomicUsize::new(0)));
+        let first = pool.checkout_async().await.unwrap();
+        let second = pool.checkout_async().await.unwrap();
+        // the checkout is pending until the runtime is returned
+        let (runtime, _) = futures_lite::future::zip(pool.checkout_async(), async move {
+            drop((first, second));
+        })
+        .await;
+        assert!(runtime.is_ok());
+    }
+}
@@ -66,6 +66,8 @@ pub enum Error {
         name: StdString,
         message: Option<StdString>,
     },
+    /// Error when reading bytecode which is invalid or was compiled by incompatible version
+    Bytecode { message: StdString },
     /// Error when restoring a Persistent in a runtime other than the original runtime.
     UnrelatedRuntime,
     /// An error from quickjs from which the specifics are unknown.
@@ -136,6 +138,21 @@ impl Error {
         matches!(self, Error::Loading { .. })
     }
 
+    /// Create bytecode error
+    pub fn new_bytecode<M>(msg: M) -> Self
+    where
+        StdString: From<M>,
+    {
+        Error::Bytecode {
+            message: msg.into(),
+        }
+    }
+
+    /// Returns whether the error is a bytecode error
+    pub fn is_bytecode(&self) -> bool {
+        matches!(self, Error::Bytecode { .. })
+    }
+
     /// Returns whether the error is a quickjs generated exception.
     pub fn is_exception(&self) -> bool {
         matches!(self, Error::Exception { .. })
@@ -358,6 +375,10 @@ impl Display for Error {
                 "IO Error: ".fmt(f)?;
                 error.fmt(f)?;
             }
+            Bytecode { message } => {
+                "Invalid bytecode: ".fmt(f)?;
+                message.fmt(f)?;
+            }
             UnrelatedRuntime => "Restoring Persistent in an unrelated runtime".fmt(f)?,
         }
         Ok(())
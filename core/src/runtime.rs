@@ -1,5 +1,13 @@
-use crate::{qjs, Ctx, Error, Function, Mut, Ref, Result, Weak};
-use std::{any::Any, ffi::CString, mem, panic, ptr::NonNull};
+use crate::{
+    qjs, Ctx, Error, Function, Mut, ParallelSend, ParallelSync, Ref, Result, StdResult, Weak,
+};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ffi::CString,
+    mem, panic,
+    ptr::NonNull,
+};
 
 #[cfg(feature = "futures")]
 mod async_runtime;
@@ -16,7 +24,10 @@ pub use qjs::JSMemoryUsage as MemoryUsage;
 use crate::{allocator::AllocatorHolder, Allocator};
 
 #[cfg(feature = "loader")]
-use crate::{loader::LoaderHolder, Loader, Resolver};
+use crate::{
+    loader::{ImportMetaInit, LoaderHolder, LoaderOpaque, ModuleRegistry},
+    Loader, ModuleInfo, Object, Resolver,
+};
 
 #[derive(Clone)]
 #[repr(transparent)]
@@ -42,6 +53,24 @@ pub struct Opaque {
     /// Async spawner
     #[cfg(feature = "futures")]
     pub spawner: Option<Spawner>,
+
+    /// Used to access module resolver from Ctx
+    #[cfg(feature = "loader")]
+    pub loader: Option<NonNull<LoaderOpaque>>,
+
+    /// The user provided initializer of `import.meta`, if any.
+    #[cfg(feature = "loader")]
+    pub import_meta_init: Option<Box<ImportMetaInit>>,
+
+    /// The registries of modules which is tracked by module loader in each context
+    #[cfg(feature = "loader")]
+    pub modules: HashMap<usize, ModuleRegistry>,
+
+    /// The user data which is stored in contexts
+    pub context_data: HashMap<(usize, TypeId), Box<dyn Any>>,
+
+    /// The user data which is stored in runtime
+    pub userdata: HashMap<TypeId, Box<dyn Any>>,
 }
 
 impl Opaque {
@@ -52,6 +81,14 @@ impl Opaque {
             runtime: runtime.weak(),
             #[cfg(feature = "futures")]
             spawner: Default::default(),
+            #[cfg(feature = "loader")]
+            loader: None,
+            #[cfg(feature = "loader")]
+            import_meta_init: None,
+            #[cfg(feature = "loader")]
+            modules: Default::default(),
+            context_data: HashMap::new(),
+            userdata: HashMap::new(),
         }
     }
 }
@@ -91,7 +128,7 @@ impl Inner {
         }
     }
 
-    #[cfg(feature = "futures")]
+    #[cfg(any(feature = "futures", feature = "loader"))]
     pub(crate) unsafe fn get_opaque(&self) -> &Opaque {
         &*(qjs::JS_GetRuntimeOpaque(self.rt.as_ptr()) as *const _)
     }
@@ -100,6 +137,18 @@ impl Inner {
         &mut *(qjs::JS_GetRuntimeOpaque(self.rt.as_ptr()) as *mut _)
     }
 
+    /// Drop the data which is attached to context
+    ///
+    /// This should be done when the context is freed or a new context is created
+    /// because the address of freed context may be reused.
+    pub(crate) fn drop_context_data(&mut self, ctx: NonNull<qjs::JSContext>) {
+        let ctx = ctx.as_ptr() as usize;
+        let opaque = unsafe { self.get_opaque_mut() };
+        opaque.context_data.retain(|(ptr, _), _| *ptr != ctx);
+        #[cfg(feature = "loader")]
+        opaque.modules.remove(&ctx);
+    }
+
     pub(crate) fn is_job_pending(&self) -> bool {
         0 != unsafe { qjs::JS_IsJobPending(self.rt.as_ptr()) }
     }
@@ -250,9 +299,48 @@ impl Runtime {
         let mut guard = self.inner.lock();
         let loader = LoaderHolder::new(resolver, loader);
         loader.set_to_runtime(guard.rt.as_ptr());
+        unsafe { guard.get_opaque_mut() }.loader = Some(loader.opaque_ptr());
         guard.loader = Some(loader);
     }
 
+    /// Set the initializer of `import.meta` objects
+    ///
+    /// The initializer is called with resolved name and `import.meta` object of each module which is loaded by the module loader.
+    /// By default the `import.meta` has the `url` property with the URL of module
+    /// (the `file:` URL for file paths or the bare name for builtin modules) and
+    /// the `resolve(specifier)` function which resolves module names using the resolver of runtime.
+    /// The initializer is called after the default properties is set, so it can override them.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+    pub fn set_import_meta_initializer<F>(&self, init: F)
+    where
+        F: for<'js> Fn(Ctx<'js>, &str, Object<'js>) -> Result<()> + 'static,
+    {
+        let mut guard = self.inner.lock();
+        unsafe { guard.get_opaque_mut() }.import_meta_init = Some(Box::new(init));
+    }
+
+    /// Get the modules which is tracked by module loader in all contexts of runtime
+    ///
+    /// The registry includes the modules which was loaded by the loader, the modules which imports other modules and
+    /// the modules which names was resolved.
+    /// The returned information can be used to build the dependency graph of modules.
+    ///
+    /// Each context has its own modules, so the module with the same name may be listed once per context.
+    /// The modules of context is forgotten when the context is dropped.
+    /// Use [`Ctx::modules`](crate::Ctx::modules) to get the modules of single context.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+    pub fn modules(&self) -> std::vec::IntoIter<ModuleInfo> {
+        let guard = self.inner.lock();
+        let modules = unsafe { guard.get_opaque() }
+            .modules
+            .values()
+            .flat_map(|registry| registry.iter().cloned())
+            .collect::<Vec<_>>();
+        modules.into_iter()
+    }
+
     /// Set the info of the runtime
     pub fn set_info<S: Into<Vec<u8>>>(&self, info: S) -> Result<()> {
         let mut guard = self.inner.lock();
@@ -312,6 +400,38 @@ impl Runtime {
         unsafe { stats.assume_init() }
     }
 
+    /// Store the user data in runtime
+    ///
+    /// The data is keyed by its type and dropped together with runtime.
+    /// It can be accessed from callbacks using [`Ctx::runtime_userdata`].
+    /// It cannot be replaced because the references to it may be in use
+    /// so the data is given back when the data of same type is already stored.
+    pub fn store_userdata<T>(&self, data: T) -> StdResult<(), T>
+    where
+        T: Any + ParallelSend + ParallelSync,
+    {
+        let mut guard = self.inner.lock();
+        let userdata = &mut unsafe { guard.get_opaque_mut() }.userdata;
+        if userdata.contains_key(&TypeId::of::<T>()) {
+            return Err(data);
+        }
+        userdata.insert(TypeId::of::<T>(), Box::new(data));
+        Ok(())
+    }
+
+    /// Get the user data of given type which is stored in runtime
+    pub fn userdata<T: Any>(&self) -> Option<&T> {
+        let mut guard = self.inner.lock();
+        let data = unsafe { guard.get_opaque_mut() }
+            .userdata
+            .get(&TypeId::of::<T>())
+            .and_then(|data| data.downcast_ref::<T>())
+            .map(|data| data as *const T);
+        mem::drop(guard);
+        // the data is boxed and never removed until the runtime is dropped
+        data.map(|data| unsafe { &*data })
+    }
+
     /// Test for pending jobs
     ///
     /// Returns true when at least one job is pending.
@@ -16,6 +16,7 @@ macro_rules! async_rt_impl {
     ($($(#[$meta:meta])* $type:ident { $join_handle:ty, $spawn_local:path, $spawn:path })*) => {
         $(
             $(#[$meta])*
+            #[derive(Clone, Copy, Debug, Default)]
             pub struct $type;
 
             $(#[$meta])*
@@ -0,0 +1,464 @@
+//! The timers built-in
+//!
+//! The [`Timers`] installs `setTimeout`, `setInterval`, `clearTimeout`, `clearInterval` and `queueMicrotask` globals.
+//! The timer tasks is spawned on the async executor of [`Runtime`](crate::Runtime) so it should be started before using timers
+//! (see [`Runtime::spawn_executor`](crate::Runtime::spawn_executor) and [`Runtime::run_executor`](crate::Runtime::run_executor)).
+//!
+//! The sleeping is provided by the [`Timer`] implementation which can be backed by any async runtime.
+//! The [`ManualClock`] can be used to control the time manually to get deterministic tests.
+//!
+//! ```
+//! # use rquickjs::{Runtime, Context, timers::{Timers, ManualClock}};
+//! # use futures_lite::future::{block_on, or};
+//! # use std::time::Duration;
+//! let rt = Runtime::new().unwrap();
+//! let ctx = Context::full(&rt).unwrap();
+//! let executor = rt.run_executor();
+//!
+//! let clock = ManualClock::new();
+//! ctx.with(|ctx| {
+//!     Timers::new(clock.clone()).init(ctx).unwrap();
+//!     ctx.eval::<(), _>("globalThis.fired = false; setTimeout(() => { fired = true; }, 100);").unwrap();
+//! });
+//!
+//! block_on(or(executor, async {
+//!     rt.idle().await;
+//!     clock.advance(Duration::from_millis(100));
+//!     rt.idle().await;
+//! }));
+//!
+//! assert!(ctx.with(|ctx| ctx.eval::<bool, _>("fired").unwrap()));
+//! ```
+
+use crate::{
+    qjs, Context, Ctx, Function, Mut, Opt, ParallelSend, Persistent, Ref, Rest, Result, Value,
+};
+use pin_project_lite::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    task::{Context as TaskContext, Poll, Waker},
+    time::Duration,
+};
+
+/// The max delay of timers in milliseconds (like in browsers and Node)
+const MAX_DELAY: f64 = 2147483647.0;
+
+/// The source of sleeping futures
+///
+/// This trait abstracts timers from async runtimes.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "timers")))]
+pub trait Timer: Clone + ParallelSend + 'static {
+    /// The sleeping future
+    type Sleep: Future<Output = ()> + ParallelSend + 'static;
+
+    /// Create future which will be resolved after the given duration
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+#[cfg(feature = "tokio")]
+impl Timer for crate::Tokio {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl Timer for crate::AsyncStd {
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+#[cfg(all(feature = "smol", feature = "parallel"))]
+impl Timer for crate::Smol {
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+/// The manually controlled clock
+///
+/// The time is changed only by calling [`ManualClock::advance`] so the timers fires deterministically.
+/// The timers with same deadline fires in order of scheduling.
+///
+/// Note that the timers which is scheduled by fired timers is relative to the advanced time,
+/// so the clock should be advanced by smaller steps with awaiting [`Runtime::idle`](crate::Runtime::idle) between to fire intervals multiple times.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "timers")))]
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    state: Ref<Mut<ClockState>>,
+}
+
+#[derive(Default)]
+struct ClockState {
+    now: Duration,
+    next_seq: u64,
+    // [ (deadline, seq, waker) ]
+    waiters: Vec<(Duration, u64, Waker)>,
+}
+
+impl ManualClock {
+    /// Create new clock which starts from zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the elapsed time since the clock was created
+    pub fn now(&self) -> Duration {
+        self.state.lock().now
+    }
+
+    /// Advance the time and wake expired sleeps
+    pub fn advance(&self, duration: Duration) {
+        let mut expired = {
+            let mut state = self.state.lock();
+            state.now += duration;
+            let now = state.now;
+            let (expired, waiters) = state
+                .waiters
+                .drain(..)
+                .partition::<Vec<_>, _>(|(deadline, _, _)| *deadline <= now);
+            state.waiters = waiters;
+            expired
+        };
+        expired.sort_by_key(|(deadline, seq, _)| (*deadline, *seq));
+        for (_, _, waker) in expired {
+            waker.wake();
+        }
+    }
+}
+
+impl Timer for ManualClock {
+    type Sleep = ManualSleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        let mut state = self.state.lock();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        ManualSleep {
+            clock: self.clone(),
+            deadline: state.now + duration,
+            seq,
+        }
+    }
+}
+
+/// The sleeping future of [`ManualClock`]
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "timers")))]
+pub struct ManualSleep {
+    clock: ManualClock,
+    deadline: Duration,
+    seq: u64,
+}
+
+impl Future for ManualSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Self::Output> {
+        let mut state = self.clock.state.lock();
+        if state.now >= self.deadline {
+            return Poll::Ready(());
+        }
+        let seq = self.seq;
+        state.waiters.retain(|(_, other, _)| *other != seq);
+        state.waiters.push((self.deadline, seq, cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+#[derive(Default)]
+struct TimersState {
+    last_id: u32,
+    // { id: waker }
+    active: HashMap<u32, Option<Waker>>,
+}
+
+impl TimersState {
+    /// Register new active timer and returns its id
+    ///
+    /// The ids starts from 1 like in browsers so the id is always truthy.
+    /// The zero and the ids of still active timers is skipped when the ids is wrapped.
+    fn add(&mut self) -> u32 {
+        loop {
+            self.last_id = self.last_id.wrapping_add(1);
+            if self.last_id != 0 && !self.active.contains_key(&self.last_id) {
+                break;
+            }
+        }
+        self.active.insert(self.last_id, None);
+        self.last_id
+    }
+
+    fn cancel(&mut self, id: u32) {
+        if let Some(Some(waker)) = self.active.remove(&id) {
+            waker.wake();
+        }
+    }
+}
+
+/// The timers built-in
+///
+/// The active timers is shared between all contexts where it is installed.
+#[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "timers")))]
+#[derive(Clone)]
+pub struct Timers<T> {
+    timer: T,
+    state: Ref<Mut<TimersState>>,
+}
+
+impl<T: Timer> Timers<T> {
+    /// Create timers which uses the given timer to sleep
+    pub fn new(timer: T) -> Self {
+        Self {
+            timer,
+            state: Default::default(),
+        }
+    }
+
+    /// Get the number of active timers
+    pub fn active(&self) -> usize {
+        self.state.lock().active.len()
+    }
+
+    /// Install the timer functions into the global object of context
+    pub fn init<'js>(&self, ctx: Ctx<'js>) -> Result<()> {
+        let globals = ctx.globals();
+
+        for (name, repeat) in &[("setTimeout", false), ("setInterval", true)] {
+            let repeat = *repeat;
+            let timers = self.clone();
+            let func = Function::new(
+                ctx,
+                schedule_fn(move |ctx, callback, delay, args| {
+                    timers.schedule(ctx, callback, delay.0, args.0, repeat)
+                }),
+            )?;
+            func.set_name(name)?;
+            globals.set(*name, func)?;
+        }
+
+        for name in &["clearTimeout", "clearInterval"] {
+            let state = self.state.clone();
+            let func = Function::new(ctx, move |id: Opt<Value>| {
+                if let Some(id) = id.0.and_then(|id| id.as_number()) {
+                    if id >= 0.0 && id <= u32::MAX as f64 {
+                        state.lock().cancel(id as u32);
+                    }
+                }
+            })?;
+            func.set_name(name)?;
+            globals.set(*name, func)?;
+        }
+
+        let func = Function::new(ctx, queue_microtask_fn(queue_microtask))?;
+        func.set_name("queueMicrotask")?;
+        globals.set("queueMicrotask", func)?;
+
+        Ok(())
+    }
+
+    fn schedule<'js>(
+        &self,
+        ctx: Ctx<'js>,
+        callback: Function<'js>,
+        delay: Option<f64>,
+        args: Vec<Value<'js>>,
+        repeat: bool,
+    ) -> Result<u32> {
+        // Like Node does
+        let delay = match delay {
+            Some(delay) if (1.0..=MAX_DELAY).contains(&delay) => delay,
+            _ => 1.0,
+        };
+        let delay = Duration::from_secs_f64(delay / 1000.0);
+
+        let context = Context::from_ctx(ctx)?;
+        let callback = Persistent::save(ctx, callback);
+        let args = args
+            .into_iter()
+            .map(|arg| Persistent::save(ctx, arg))
+            .collect::<Vec<_>>();
+
+        let id = self.state.lock().add();
+
+        let timer = self.timer.clone();
+        let state = self.state.clone();
+        ctx.spawn(async move {
+            loop {
+                let fired = Cancellable {
+                    sleep: timer.sleep(delay),
+                    id,
+                    state: state.clone(),
+                }
+                .await;
+                if !fired {
+                    break;
+                }
+                if !repeat {
+                    state.lock().active.remove(&id);
+                }
+                context.with(|ctx| {
+                    let result = callback.clone().restore(ctx).and_then(|callback| {
+                        let args = args
+                            .iter()
+                            .map(|arg| arg.clone().restore(ctx))
+                            .collect::<Result<Vec<_>>>()?;
+                        callback.call::<_, ()>((Rest(args),))
+                    });
+                    if let Err(error) = result {
+                        eprintln!("Error when timer callback executing: {}", error);
+                    }
+                });
+                if !repeat {
+                    break;
+                }
+            }
+            // The values should be released while runtime is locked
+            context.with(|_| {
+                drop(callback);
+                drop(args);
+            });
+        });
+
+        Ok(id)
+    }
+}
+
+pin_project! {
+    /// The sleeping which can be cancelled by clearing timer
+    struct Cancellable<S> {
+        #[pin]
+        sleep: S,
+        id: u32,
+        state: Ref<Mut<TimersState>>,
+    }
+}
+
+impl<S: Future<Output = ()>> Future for Cancellable<S> {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.state.lock().active.get_mut(this.id) {
+            Some(waker) => *waker = Some(cx.waker().clone()),
+            None => return Poll::Ready(false),
+        }
+        this.sleep.poll(cx).map(|_| true)
+    }
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn schedule_fn<F>(func: F) -> F
+where
+    F: for<'js> Fn(Ctx<'js>, Function<'js>, Opt<f64>, Rest<Value<'js>>) -> Result<u32>,
+{
+    func
+}
+
+/// Helps to infer the same lifetime for context and arguments
+fn queue_microtask_fn<F>(func: F) -> F
+where
+    F: for<'js> Fn(Ctx<'js>, Function<'js>) -> Result<()>,
+{
+    func
+}
+
+fn queue_microtask<'js>(ctx: Ctx<'js>, callback: Function<'js>) -> Result<()> {
+    unsafe extern "C" fn job(
+        ctx: *mut qjs::JSContext,
+        _argc: qjs::c_int,
+        argv: *mut qjs::JSValue,
+    ) -> qjs::JSValue {
+        let result = qjs::JS_Call(ctx, *argv, qjs::JS_UNDEFINED, 0, std::ptr::null_mut());
+        if qjs::JS_IsException(result) {
+            return result;
+        }
+        qjs::JS_FreeValue(ctx, result);
+        qjs::JS_UNDEFINED
+    }
+
+    let mut argv = [callback.0.as_js_value()];
+    if unsafe { qjs::JS_EnqueueJob(ctx.as_ptr(), Some(job), 1, argv.as_mut_ptr()) } < 0 {
+        return Err(unsafe { ctx.get_exception() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+    use futures_lite::future::{block_on, or};
+
+    #[test]
+    fn manual_clock() {
+        let rt = Runtime::new().unwrap();
+        let ctx = Context::full(&rt).unwrap();
+        let executor = rt.run_executor();
+        let clock = ManualClock::new();
+        let timers = Timers::new(clock.clone());
+
+        ctx.with(|ctx| {
+            timers.init(ctx).unwrap();
+            let _: Value = ctx
+                .eval(
+                    r#"
+                    globalThis.log = [];
+                    setTimeout(a => log.push(a), 10, 'a');
+                    const i = setInterval(() => {
+                        log.push('i');
+                        if (log.length > 3) clearInterval(i);
+                    }, 5);
+                    clearTimeout(setTimeout(() => log.push('never'), 1));
+                    queueMicrotask(() => log.push('m'));
+                "#,
+                )
+                .unwrap();
+        });
+
+        let log = || ctx.with(|ctx| ctx.eval::<Vec<StdString>, _>("log").unwrap());
+
+        block_on(or(executor, async {
+            let step = |ms| {
+                clock.advance(Duration::from_millis(ms));
+                rt.idle()
+            };
+            rt.idle().await;
+            assert_eq!(log(), ["m"]);
+            step(5).await;
+            assert_eq!(log(), ["m", "i"]);
+            step(5).await;
+            assert_eq!(log(), ["m", "i", "a", "i"]);
+            step(5).await;
+            assert_eq!(log(), ["m", "i", "a", "i"]);
+        }));
+
+        assert_eq!(timers.active(), 0);
+        assert_eq!(clock.now(), Duration::from_millis(15));
+    }
+
+    #[test]
+    fn timer_ids() {
+        let mut state = TimersState::default();
+        assert_eq!(state.add(), 1);
+        assert_eq!(state.add(), 2);
+
+        state.last_id = u32::MAX - 1;
+        assert_eq!(state.add(), u32::MAX);
+        // the zero and the still active ids is skipped
+        assert_eq!(state.add(), 3);
+        state.cancel(1);
+        state.last_id = u32::MAX;
+        assert_eq!(state.add(), 1);
+    }
+}
@@ -1,8 +1,10 @@
 mod array;
 mod atom;
 mod bigint;
+pub(crate) mod bytecode;
 mod convert;
 mod function;
+mod inspect;
 mod module;
 mod object;
 mod string;
@@ -26,12 +28,15 @@ pub use convert::*;
 pub use function::{
     AsArguments, AsFunction, Func, Function, Method, MutFn, OnceFn, Opt, Rest, This,
 };
+pub use inspect::InspectOptions;
 pub use object::{Filter, Object, ObjectDef};
 pub use string::String;
 pub use symbol::Symbol;
 
 #[cfg(feature = "array-buffer")]
 pub use array_buffer::ArrayBuffer;
+#[cfg(feature = "crypto")]
+pub(crate) use array_buffer::BufferClass;
 #[cfg(feature = "array-buffer")]
 pub use typed_array::TypedArray;
 
@@ -6,6 +6,85 @@ use std::{
     slice,
 };
 
+/// The class of builtin buffer or view object
+#[cfg(any(
+    feature = "encoding",
+    feature = "fetch",
+    feature = "fs",
+    feature = "crypto"
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BufferClass {
+    ArrayBuffer,
+    SharedArrayBuffer,
+    Uint8ClampedArray,
+    Int8Array,
+    Uint8Array,
+    Int16Array,
+    Uint16Array,
+    Int32Array,
+    Uint32Array,
+    BigInt64Array,
+    BigUint64Array,
+    Float32Array,
+    Float64Array,
+    DataView,
+}
+
+#[cfg(any(
+    feature = "encoding",
+    feature = "fetch",
+    feature = "fs",
+    feature = "crypto"
+))]
+impl BufferClass {
+    /// The classes in order of its ids in `quickjs.c` (which is built with `CONFIG_BIGNUM`)
+    const ALL: [Self; 14] = [
+        Self::ArrayBuffer,
+        Self::SharedArrayBuffer,
+        Self::Uint8ClampedArray,
+        Self::Int8Array,
+        Self::Uint8Array,
+        Self::Int16Array,
+        Self::Uint16Array,
+        Self::Int32Array,
+        Self::Uint32Array,
+        Self::BigInt64Array,
+        Self::BigUint64Array,
+        Self::Float32Array,
+        Self::Float64Array,
+        Self::DataView,
+    ];
+
+    /// The class id of `ArrayBuffer` in `quickjs.c`
+    const FIRST_ID: qjs::JSClassID = 19;
+
+    /// Get the class of value
+    ///
+    /// The class is checked by id so it cannot be spoofed and no JS code is run.
+    pub fn of(val: &Value) -> Option<Self> {
+        let val = val.as_js_value();
+        Self::ALL
+            .iter()
+            .zip(Self::FIRST_ID..)
+            .find(|(_, id)| !unsafe { qjs::JS_GetOpaque(val, *id) }.is_null())
+            .map(|(class, _)| *class)
+    }
+
+    /// Check whether the class is a typed array of integers
+    #[cfg(feature = "crypto")]
+    pub fn is_integer_array(self) -> bool {
+        !matches!(
+            self,
+            Self::ArrayBuffer
+                | Self::SharedArrayBuffer
+                | Self::Float32Array
+                | Self::Float64Array
+                | Self::DataView
+        )
+    }
+}
+
 /// Rust representation of a javascript object of class ArrayBuffer.
 ///
 #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "array-buffer")))]
@@ -113,6 +192,65 @@ impl<'js> ArrayBuffer<'js> {
         }
     }
 
+    /// Copy the bytes of `ArrayBuffer` or any of its views like typed arrays and `DataView`
+    #[cfg(any(
+        feature = "encoding",
+        feature = "fetch",
+        feature = "fs",
+        feature = "crypto"
+    ))]
+    pub(crate) fn copy_bytes(value: &Value<'js>) -> Option<Vec<u8>> {
+        let (len, ptr) = match BufferClass::of(value)? {
+            BufferClass::ArrayBuffer | BufferClass::SharedArrayBuffer => Self::get_raw(value)?,
+            BufferClass::DataView => {
+                // There is no C API for data views
+                let view = value.as_object()?;
+                let buffer = view.get::<_, Value>("buffer").ok()?;
+                let (len, ptr) = Self::get_raw(&buffer)?;
+                let offset: usize = view.get("byteOffset").ok()?;
+                let length: usize = view.get("byteLength").ok()?;
+                let bytes = unsafe { slice::from_raw_parts(ptr, len) };
+                return bytes
+                    .get(offset..offset.checked_add(length)?)
+                    .map(|bytes| bytes.into());
+            }
+            _ => {
+                let (len, _, ptr) = Self::get_typed_raw(value)?;
+                (len, ptr)
+            }
+        };
+        Some(unsafe { slice::from_raw_parts(ptr, len) }.into())
+    }
+
+    /// Get the length in bytes, the item size and the pointer to data of typed array
+    pub(crate) fn get_typed_raw(val: &Value<'js>) -> Option<(usize, usize, *mut u8)> {
+        let ctx = val.ctx;
+        let val = val.as_js_value();
+        let mut off = MaybeUninit::<qjs::size_t>::uninit();
+        let mut len = MaybeUninit::<qjs::size_t>::uninit();
+        let mut stp = MaybeUninit::<qjs::size_t>::uninit();
+        let buf = unsafe {
+            let val = qjs::JS_GetTypedArrayBuffer(
+                ctx.as_ptr(),
+                val,
+                off.as_mut_ptr(),
+                len.as_mut_ptr(),
+                stp.as_mut_ptr(),
+            );
+            ctx.handle_exception(val).ok()?;
+            Value::from_js_value(ctx, val)
+        };
+        let off = unsafe { off.assume_init() } as usize;
+        let len = unsafe { len.assume_init() } as usize;
+        let stp = unsafe { stp.assume_init() } as usize;
+        let (full_len, ptr) = Self::get_raw(&buf)?;
+        if (off + len) > full_len {
+            return None;
+        }
+        let ptr = unsafe { ptr.add(off) };
+        Some((len, stp, ptr))
+    }
+
     pub(crate) fn get_raw(val: &Value<'js>) -> Option<(usize, *mut u8)> {
         let ctx = val.ctx;
         let val = val.as_js_value();
@@ -120,6 +258,8 @@ impl<'js> ArrayBuffer<'js> {
         let ptr = unsafe { qjs::JS_GetArrayBuffer(ctx.as_ptr(), size.as_mut_ptr(), val) };
 
         if ptr.is_null() {
+            // Clear the exception which is thrown for other values and detached buffers
+            unsafe { qjs::JS_FreeValue(ctx.as_ptr(), qjs::JS_GetException(ctx.as_ptr())) };
             None
         } else {
             let len = unsafe { size.assume_init() } as _;
@@ -182,6 +322,72 @@ impl<'js> IntoJs<'js> for ArrayBuffer<'js> {
 mod test {
     use crate::*;
 
+    #[cfg(any(
+        feature = "encoding",
+        feature = "fetch",
+        feature = "fs",
+        feature = "crypto"
+    ))]
+    #[test]
+    fn buffer_class() {
+        use super::BufferClass;
+        test_with(|ctx| {
+            let class = |source: &str| BufferClass::of(&ctx.eval(source).unwrap());
+            assert_eq!(class("new ArrayBuffer(1)"), Some(BufferClass::ArrayBuffer));
+            assert_eq!(
+                class("new Uint8ClampedArray(1)"),
+                Some(BufferClass::Uint8ClampedArray)
+            );
+            assert_eq!(
+                class("new BigUint64Array(1)"),
+                Some(BufferClass::BigUint64Array)
+            );
+            assert_eq!(
+                class("new Float64Array(1)"),
+                Some(BufferClass::Float64Array)
+            );
+            assert_eq!(
+                class("new DataView(new ArrayBuffer(1))"),
+                Some(BufferClass::DataView)
+            );
+            assert_eq!(
+                class("Object.setPrototypeOf({}, Uint8Array.prototype)"),
+                None
+            );
+            assert_eq!(class("/a/"), None);
+            assert_eq!(class("1"), None);
+        });
+    }
+
+    #[cfg(any(
+        feature = "encoding",
+        feature = "fetch",
+        feature = "fs",
+        feature = "crypto"
+    ))]
+    #[test]
+    fn copy_bytes() {
+        test_with(|ctx| {
+            let copy = |source: &str| ArrayBuffer::copy_bytes(&ctx.eval(source).unwrap());
+            assert_eq!(copy("Uint8Array.of(1, 2, 3).buffer"), Some(vec![1, 2, 3]));
+            assert_eq!(copy("Uint8Array.of(1, 2, 3).subarray(1)"), Some(vec![2, 3]));
+            assert_eq!(
+                copy("new DataView(Uint8Array.of(1, 2, 3).buffer, 1, 1)"),
+                Some(vec![2])
+            );
+            assert_eq!(
+                copy("({ buffer: new ArrayBuffer(2), byteOffset: 0, byteLength: 2 })"),
+                None
+            );
+            assert_eq!(copy("'abc'"), None);
+            assert_eq!(copy("({})"), None);
+            // no exception is left pending
+            let exception =
+                unsafe { Value::from_js_value(ctx, qjs::JS_GetException(ctx.as_ptr())) };
+            assert_eq!(exception.type_of(), Type::Null);
+        });
+    }
+
     #[test]
     fn from_javascript_i8() {
         test_with(|ctx| {
@@ -0,0 +1,155 @@
+use crate::{qjs, Error, Result};
+use std::convert::TryInto;
+
+/// The magic bytes of bytecode header
+const MAGIC: &[u8; 4] = b"RQJS";
+
+/// The version of header format
+const FORMAT: u8 = 1;
+
+/// The bytecode uses big-endian byte order
+const FLAG_BIG_ENDIAN: u8 = 1 << 0;
+
+/// The bytecode may contain big numbers
+const FLAG_BIGNUM: u8 = 1 << 1;
+
+/// The QuickJS is always built with `CONFIG_BIGNUM`
+const BUILD_FLAGS: u8 = FLAG_BIGNUM;
+
+/// The version of QuickJS and rquickjs which the bytecode is compatible with
+fn version() -> String {
+    format!("{}/{}", qjs::VERSION, env!("CARGO_PKG_VERSION"))
+}
+
+fn pointer_width() -> u8 {
+    (std::mem::size_of::<usize>() * 8) as u8
+}
+
+/// Prepend the versioned header to the bytecode
+///
+/// The header layout:
+///
+/// - magic (4 bytes)
+/// - format (u8)
+/// - flags (u8)
+/// - pointer width in bits (u8)
+/// - length of version string (u8)
+/// - version string
+/// - checksum of bytecode (u64 le)
+pub(crate) fn add_header(bytecode: &[u8], byte_swap: bool) -> Vec<u8> {
+    let version = version();
+    let mut flags = BUILD_FLAGS;
+    if cfg!(target_endian = "big") != byte_swap {
+        flags |= FLAG_BIG_ENDIAN;
+    }
+
+    let mut data = Vec::with_capacity(MAGIC.len() + 4 + version.len() + 8 + bytecode.len());
+    data.extend_from_slice(MAGIC);
+    data.push(FORMAT);
+    data.push(flags);
+    data.push(pointer_width());
+    data.push(version.len() as u8);
+    data.extend_from_slice(version.as_bytes());
+    data.extend_from_slice(&checksum(bytecode).to_le_bytes());
+    data.extend_from_slice(bytecode);
+    data
+}
+
+/// Validate the header and returns the bytecode without it
+pub(crate) fn strip_header(data: &[u8]) -> Result<&[u8]> {
+    let mut input = data;
+
+    if take(&mut input, MAGIC.len())? != MAGIC {
+        return Err(Error::new_bytecode("Missing bytecode header"));
+    }
+
+    let format = take(&mut input, 1)?[0];
+    if format != FORMAT {
+        return Err(Error::new_bytecode(format!(
+            "Unsupported bytecode header format {} (expected {})",
+            format, FORMAT
+        )));
+    }
+
+    let flags = take(&mut input, 1)?[0];
+    if (flags & FLAG_BIG_ENDIAN != 0) != cfg!(target_endian = "big") {
+        return Err(Error::new_bytecode(
+            "Bytecode was compiled for different byte order",
+        ));
+    }
+    if flags & !FLAG_BIG_ENDIAN != BUILD_FLAGS {
+        return Err(Error::new_bytecode(
+            "Bytecode was compiled with different QuickJS configuration",
+        ));
+    }
+
+    let width = take(&mut input, 1)?[0];
+    if width != pointer_width() {
+        return Err(Error::new_bytecode(format!(
+            "Bytecode was compiled for {}-bit target (expected {}-bit)",
+            width,
+            pointer_width()
+        )));
+    }
+
+    let version_len = take(&mut input, 1)?[0] as usize;
+    let version_data = take(&mut input, version_len)?;
+    let expected_version = version();
+    if version_data != expected_version.as_bytes() {
+        return Err(Error::new_bytecode(format!(
+            "Bytecode was compiled by {} (expected {})",
+            String::from_utf8_lossy(version_data),
+            expected_version
+        )));
+    }
+
+    let expected_checksum = u64::from_le_bytes(take(&mut input, 8)?.try_into().unwrap());
+    if checksum(input) != expected_checksum {
+        return Err(Error::new_bytecode("Bytecode checksum mismatch"));
+    }
+
+    Ok(input)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(Error::new_bytecode("Bytecode header is truncated"));
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+/// The FNV-1a hash which is stable between builds unlike the std hashers
+pub(crate) fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let data = add_header(b"bytecode", false);
+        assert_eq!(strip_header(&data).unwrap(), b"bytecode");
+
+        let swapped = add_header(b"bytecode", true);
+        assert!(strip_header(&swapped)
+            .unwrap_err()
+            .to_string()
+            .contains("byte order"));
+
+        let mut corrupted = data.clone();
+        *corrupted.last_mut().unwrap() ^= 0xff;
+        assert!(strip_header(&corrupted)
+            .unwrap_err()
+            .to_string()
+            .contains("checksum"));
+
+        assert!(strip_header(b"bytecode").unwrap_err().is_bytecode());
+        assert!(strip_header(&data[..10]).unwrap_err().is_bytecode());
+    }
+}
@@ -0,0 +1,622 @@
+use crate::{
+    qjs, Array, Atom, Coerced, Ctx, Filter, Function, Object, Result, StdString, Type, Value,
+};
+use std::mem::MaybeUninit;
+
+/// The max number of bytes of `ArrayBuffer` which is shown
+const MAX_BUFFER_BYTES: usize = 50;
+
+/// The options of value inspection
+///
+/// See [`Value::inspect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InspectOptions {
+    /// The max depth of nested objects which is shown (`None` means unlimited)
+    pub depth: Option<usize>,
+    /// The max number of items of arrays, maps and sets which is shown
+    pub max_array_length: usize,
+    /// The max number of characters of strings which is shown
+    pub max_string_length: usize,
+    /// Show non-enumerable properties
+    pub show_hidden: bool,
+    /// Call getters to show values of accessor properties
+    pub getters: bool,
+    /// The max length of line before splitting objects to multiple lines
+    pub break_length: usize,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            depth: Some(2),
+            max_array_length: 100,
+            max_string_length: 10000,
+            show_hidden: false,
+            getters: false,
+            break_length: 80,
+        }
+    }
+}
+
+impl<'js> Value<'js> {
+    /// Render the value in human-readable form like `util.inspect` in Node does
+    ///
+    /// Unlike the [`Debug`](std::fmt::Debug) output of values the inspection may run JS code (i.e. proxy traps),
+    /// so it should be called explicitly.
+    ///
+    /// ```
+    /// # use rquickjs::{Runtime, Context, InspectOptions, Value};
+    /// # let rt = Runtime::new().unwrap();
+    /// # let ctx = Context::full(&rt).unwrap();
+    /// # ctx.with(|ctx| {
+    /// let value: Value = ctx.eval("const a = { b: [1, 'c'], d: new Map([[1, 2]]) }; a.a = a; a").unwrap();
+    /// assert_eq!(
+    ///     value.inspect(InspectOptions::default()).unwrap(),
+    ///     "{ b: [ 1, 'c' ], d: Map(1) { 1 => 2 }, a: [Circular] }"
+    /// );
+    /// # });
+    /// ```
+    pub fn inspect(&self, options: InspectOptions) -> Result<StdString> {
+        Inspector {
+            ctx: self.ctx,
+            options,
+            seen: Vec::new(),
+            indent: 0,
+        }
+        .value(self, 0)
+    }
+}
+
+/// The value of own property
+enum Prop<'js> {
+    Value(Value<'js>),
+    Accessor {
+        getter: Option<Function<'js>>,
+        setter: bool,
+    },
+}
+
+struct Inspector<'js> {
+    ctx: Ctx<'js>,
+    options: InspectOptions,
+    // The stack of objects which is inspecting now to detect cycles
+    seen: Vec<Value<'js>>,
+    indent: usize,
+}
+
+impl<'js> Inspector<'js> {
+    fn value(&mut self, value: &Value<'js>, depth: usize) -> Result<StdString> {
+        Ok(match value.type_of() {
+            Type::Uninitialized => "<uninitialized>".into(),
+            Type::Undefined => "undefined".into(),
+            Type::Null => "null".into(),
+            Type::Bool => unsafe { value.get_bool() }.to_string(),
+            Type::Int => unsafe { value.get_int() }.to_string(),
+            Type::Float => {
+                let number = unsafe { value.get_float() };
+                if number == 0.0 && number.is_sign_negative() {
+                    "-0".into()
+                } else {
+                    value.get::<Coerced<StdString>>()?.0
+                }
+            }
+            Type::BigInt => format!("{}n", value.get::<Coerced<StdString>>()?.0),
+            Type::String => self.string(&value.get::<StdString>()?),
+            Type::Symbol => symbol(self.ctx, value)?,
+            Type::Module => "[Module]".into(),
+            Type::Array | Type::Function | Type::Object => {
+                if self.seen.contains(value) {
+                    return Ok("[Circular]".into());
+                }
+                self.seen.push(value.clone());
+                let result = self.object(value.as_object().unwrap(), depth);
+                self.seen.pop();
+                result?
+            }
+            Type::Unknown => value.get::<Coerced<StdString>>()?.0,
+        })
+    }
+
+    fn string(&self, value: &str) -> StdString {
+        let quote = if value.contains('\'') && !value.contains('"') {
+            '"'
+        } else {
+            '\''
+        };
+        let mut out = StdString::with_capacity(value.len() + 2);
+        out.push(quote);
+        let mut chars = value.chars();
+        for c in chars.by_ref().take(self.options.max_string_length) {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                _ if c == quote => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                _ if c.is_control() => out.push_str(&format!("\\x{:02X}", c as u32)),
+                _ => out.push(c),
+            }
+        }
+        out.push(quote);
+        let rest = chars.count();
+        if rest > 0 {
+            out.push_str(&format!("... {} more character{}", rest, plural(rest)));
+        }
+        out
+    }
+
+    fn object(&mut self, object: &Object<'js>, depth: usize) -> Result<StdString> {
+        let tag = self.tag(object)?;
+        let name = constructor_name(object)?;
+
+        if object.is_function() {
+            let name: StdString = object.get("name").unwrap_or_default();
+            return Ok(if name.is_empty() {
+                "[Function (anonymous)]".into()
+            } else {
+                format!("[Function: {}]", name)
+            });
+        }
+
+        if object.is_error() {
+            let message = object.as_value().get::<Coerced<StdString>>()?.0;
+            let stack: Option<StdString> = object.get("stack")?;
+            return Ok(match stack {
+                Some(stack) if !stack.trim().is_empty() => {
+                    format!("{}\n{}", message, stack.trim_end())
+                }
+                _ => format!("[{}]", message),
+            });
+        }
+
+        match tag.as_str() {
+            "Date" => {
+                let to_iso: Function = object.get("toISOString")?;
+                return Ok(to_iso
+                    .call((crate::This(object.clone()),))
+                    .unwrap_or_else(|_| "Invalid Date".into()));
+            }
+            "RegExp" => return Ok(object.as_value().get::<Coerced<StdString>>()?.0),
+            "Number" | "Boolean" | "String" | "Symbol" | "BigInt" => {
+                let value_of: Function = object.get("valueOf")?;
+                let value: Value = value_of.call((crate::This(object.clone()),))?;
+                return Ok(format!("[{}: {}]", tag, self.value(&value, depth)?));
+            }
+            _ => {}
+        }
+
+        let prefix = match &name {
+            Some(name) => name.clone(),
+            None => "[Object: null prototype]".into(),
+        };
+
+        if let Some(max_depth) = self.options.depth {
+            if depth > max_depth {
+                return Ok(if object.is_array() {
+                    "[Array]".into()
+                } else {
+                    format!("[{}]", name.as_deref().unwrap_or("Object"))
+                });
+            }
+        }
+
+        self.indent += 2;
+        let result = self.entries(object, &tag, &prefix, depth);
+        self.indent -= 2;
+        let (prefix, open, close, entries) = result?;
+        Ok(self.reduce(&prefix, open, close, entries))
+    }
+
+    /// Collect the entries of object
+    #[allow(clippy::type_complexity)]
+    fn entries(
+        &mut self,
+        object: &Object<'js>,
+        tag: &str,
+        prefix: &str,
+        depth: usize,
+    ) -> Result<(StdString, char, char, Vec<StdString>)> {
+        let mut entries = Vec::new();
+
+        if object.is_array() {
+            let len: usize = object.get("length")?;
+            self.items(object, len, depth, &mut entries)?;
+            let prefix = if prefix == "Array" {
+                StdString::new()
+            } else {
+                format!("{}({}) ", prefix, len)
+            };
+            return Ok((prefix, '[', ']', entries));
+        }
+
+        match tag {
+            "Map" | "Set" => {
+                let size: usize = object.get("size")?;
+                let items = array_from(self.ctx, object)?;
+                for item in items.iter::<Value>().take(self.options.max_array_length) {
+                    let item = item?;
+                    entries.push(if tag == "Map" {
+                        let item = Array::from_value(item)?;
+                        format!(
+                            "{} => {}",
+                            self.value(&item.get(0)?, depth + 1)?,
+                            self.value(&item.get(1)?, depth + 1)?
+                        )
+                    } else {
+                        self.value(&item, depth + 1)?
+                    });
+                }
+                more_items(size, self.options.max_array_length, &mut entries);
+                self.props(object, depth, &mut entries)?;
+                return Ok((format!("{}({}) ", prefix, size), '{', '}', entries));
+            }
+            "Int8Array" | "Uint8Array" | "Uint8ClampedArray" | "Int16Array" | "Uint16Array"
+            | "Int32Array" | "Uint32Array" | "Float32Array" | "Float64Array" | "BigInt64Array"
+            | "BigUint64Array" => {
+                let len: usize = object.get("length")?;
+                self.items(object, len, depth, &mut entries)?;
+                return Ok((format!("{}({}) ", prefix, len), '[', ']', entries));
+            }
+            "ArrayBuffer" | "SharedArrayBuffer" => {
+                let len: usize = object.get("byteLength")?;
+                let uint8_array: Function = self.ctx.globals().get("Uint8Array")?;
+                let bytes: Object = uint8_array.construct((object.clone(),))?;
+                let mut contents = (0..len.min(MAX_BUFFER_BYTES))
+                    .map(|index| {
+                        bytes
+                            .get(index as u32)
+                            .map(|byte: u8| format!("{:02x}", byte))
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .join(" ");
+                if len > MAX_BUFFER_BYTES {
+                    let rest = len - MAX_BUFFER_BYTES;
+                    contents.push_str(&format!(" ... {} more byte{}", rest, plural(rest)));
+                }
+                entries.push(format!("[Uint8Contents]: <{}>", contents));
+                entries.push(format!("byteLength: {}", len));
+                return Ok((format!("{} ", prefix), '{', '}', entries));
+            }
+            _ => {}
+        }
+
+        self.props(object, depth, &mut entries)?;
+        let prefix = match prefix {
+            "Object" => StdString::new(),
+            _ => format!("{} ", prefix),
+        };
+        Ok((prefix, '{', '}', entries))
+    }
+
+    /// Collect indexed items of array-like object
+    fn items(
+        &mut self,
+        object: &Object<'js>,
+        len: usize,
+        depth: usize,
+        entries: &mut Vec<StdString>,
+    ) -> Result<()> {
+        for index in 0..len.min(self.options.max_array_length) {
+            let item: Value = object.get(index as u32)?;
+            entries.push(self.value(&item, depth + 1)?);
+        }
+        more_items(len, self.options.max_array_length, entries);
+        Ok(())
+    }
+
+    /// Collect own properties (excluding array indices)
+    fn props(
+        &mut self,
+        object: &Object<'js>,
+        depth: usize,
+        entries: &mut Vec<StdString>,
+    ) -> Result<()> {
+        let mut filter = Filter::new().string().symbol();
+        if !self.options.show_hidden {
+            filter = filter.enum_only();
+        }
+        for key in object.own_keys::<Atom>(filter) {
+            let key = key?;
+            let key_value = key.to_value()?;
+            let key_str = if key_value.is_symbol() {
+                format!("[{}]", symbol(self.ctx, &key_value)?)
+            } else {
+                let key = key.to_string()?;
+                if is_identifier(&key) {
+                    key
+                } else {
+                    self.string(&key)
+                }
+            };
+            let value = match own_property(self.ctx, object, &key)? {
+                Some(Prop::Value(value)) => self.value(&value, depth + 1)?,
+                Some(Prop::Accessor { getter, setter }) => {
+                    let kind = match (&getter, setter) {
+                        (Some(_), true) => "Getter/Setter",
+                        (Some(_), false) => "Getter",
+                        (None, _) => "Setter",
+                    };
+                    match getter {
+                        Some(getter) if self.options.getters => {
+                            let value: Value = getter.call((crate::This(object.clone()),))?;
+                            format!("[{}: {}]", kind, self.value(&value, depth + 1)?)
+                        }
+                        _ => format!("[{}]", kind),
+                    }
+                }
+                None => continue,
+            };
+            entries.push(format!("{}: {}", key_str, value));
+        }
+        Ok(())
+    }
+
+    /// Join entries into single line or split it to multiple lines when it is too long
+    fn reduce(&self, prefix: &str, open: char, close: char, entries: Vec<StdString>) -> StdString {
+        if entries.is_empty() {
+            return format!("{}{}{}", prefix, open, close);
+        }
+        let len = self.indent
+            + prefix.len()
+            + entries.iter().map(|entry| entry.len() + 2).sum::<usize>()
+            + 2;
+        if len <= self.options.break_length && entries.iter().all(|entry| !entry.contains('\n')) {
+            format!("{}{} {} {}", prefix, open, entries.join(", "), close)
+        } else {
+            let indent = " ".repeat(self.indent + 2);
+            format!(
+                "{}{}\n{}{}\n{}{}",
+                prefix,
+                open,
+                indent,
+                entries.join(&format!(",\n{}", indent)),
+                " ".repeat(self.indent),
+                close
+            )
+        }
+    }
+
+    /// Get the class tag of object using `Object.prototype.toString`
+    fn tag(&self, object: &Object<'js>) -> Result<StdString> {
+        let object_ctor: Object = self.ctx.globals().get("Object")?;
+        let proto: Object = object_ctor.get("prototype")?;
+        let to_string: Function = proto.get("toString")?;
+        let tag: StdString = to_string.call((crate::This(object.clone()),))?;
+        Ok(tag
+            .strip_prefix("[object ")
+            .and_then(|tag| tag.strip_suffix(']'))
+            .unwrap_or(&tag)
+            .into())
+    }
+}
+
+/// Get the name of constructor of object or `None` for objects without prototype
+fn constructor_name<'js>(object: &Object<'js>) -> Result<Option<StdString>> {
+    let proto: Value = unsafe {
+        Value::from_js_value(
+            object.0.ctx,
+            qjs::JS_GetPrototype(object.0.ctx.as_ptr(), object.0.as_js_value()),
+        )
+    };
+    if !proto.is_object() {
+        return Ok(None);
+    }
+    let ctor: Option<Object> = object.get("constructor").ok();
+    Ok(Some(
+        ctor.and_then(|ctor| ctor.get::<_, StdString>("name").ok())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "Object".into()),
+    ))
+}
+
+fn own_property<'js>(
+    ctx: Ctx<'js>,
+    object: &Object<'js>,
+    key: &Atom<'js>,
+) -> Result<Option<Prop<'js>>> {
+    let mut desc = MaybeUninit::<qjs::JSPropertyDescriptor>::uninit();
+    let res = unsafe {
+        qjs::JS_GetOwnProperty(
+            ctx.as_ptr(),
+            desc.as_mut_ptr(),
+            object.0.as_js_value(),
+            key.atom,
+        )
+    };
+    if res < 0 {
+        return Err(unsafe { ctx.get_exception() });
+    }
+    if res == 0 {
+        return Ok(None);
+    }
+    let desc = unsafe { desc.assume_init() };
+    let (value, getter, setter) = unsafe {
+        (
+            Value::from_js_value(ctx, desc.value),
+            Value::from_js_value(ctx, desc.getter),
+            Value::from_js_value(ctx, desc.setter),
+        )
+    };
+    Ok(Some(
+        if desc.flags & qjs::JS_PROP_GETSET as qjs::c_int != 0 {
+            Prop::Accessor {
+                getter: getter.into_function(),
+                setter: setter.is_function(),
+            }
+        } else {
+            Prop::Value(value)
+        },
+    ))
+}
+
+fn symbol<'js>(ctx: Ctx<'js>, value: &Value<'js>) -> Result<StdString> {
+    let description = Atom::from_value(ctx, value).to_string()?;
+    Ok(format!("Symbol({})", description))
+}
+
+fn array_from<'js>(ctx: Ctx<'js>, object: &Object<'js>) -> Result<Array<'js>> {
+    let array: Object = ctx.globals().get("Array")?;
+    let from: Function = array.get("from")?;
+    from.call((object.clone(),))
+}
+
+fn more_items(len: usize, max: usize, entries: &mut Vec<StdString>) {
+    if len > max {
+        let rest = len - max;
+        entries.push(format!("... {} more item{}", rest, plural(rest)));
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.is_alphabetic() || c == '_' || c == '$')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn inspect(source: &str, options: InspectOptions) -> StdString {
+        test_with(|ctx| {
+            let value: Value = ctx.eval(source).unwrap();
+            value.inspect(options).unwrap()
+        })
+    }
+
+    #[test]
+    fn primitives() {
+        let options = InspectOptions::default();
+        assert_eq!(inspect("1", options), "1");
+        assert_eq!(inspect("-0", options), "-0");
+        assert_eq!(inspect("1.5", options), "1.5");
+        assert_eq!(inspect("1e21", options), "1e+21");
+        assert_eq!(inspect("10n", options), "10n");
+        assert_eq!(inspect("'a\\nb'", options), "'a\\nb'");
+        assert_eq!(inspect("\"it's\"", options), "\"it's\"");
+        assert_eq!(inspect("Symbol('s')", options), "Symbol(s)");
+        assert_eq!(inspect("null", options), "null");
+    }
+
+    #[test]
+    fn objects() {
+        let options = InspectOptions::default();
+        assert_eq!(inspect("({})", options), "{}");
+        assert_eq!(
+            inspect("({ a: 1, 'b-c': [1, 'x'], [Symbol('s')]: true })", options),
+            "{ a: 1, 'b-c': [ 1, 'x' ], [Symbol(s)]: true }"
+        );
+        assert_eq!(
+            inspect("({ a: { b: { c: { d: 1 } } } })", options),
+            "{ a: { b: { c: [Object] } } }"
+        );
+        assert_eq!(
+            inspect(
+                "({ a: { b: { c: { d: 1 } } } })",
+                InspectOptions {
+                    depth: None,
+                    ..options
+                }
+            ),
+            "{ a: { b: { c: { d: 1 } } } }"
+        );
+        assert_eq!(
+            inspect("const a = [1]; a.push(a); a", options),
+            "[ 1, [Circular] ]"
+        );
+        assert_eq!(
+            inspect(
+                "class Point { constructor() { this.x = 1; } }; new Point()",
+                options
+            ),
+            "Point { x: 1 }"
+        );
+        assert_eq!(
+            inspect("Object.create(null)", options),
+            "[Object: null prototype] {}"
+        );
+        assert_eq!(
+            inspect("function f() {}; [f, () => {}]", options),
+            "[ [Function: f], [Function (anonymous)] ]"
+        );
+    }
+
+    #[test]
+    fn accessors() {
+        let source = "({ get a() { return 1; }, set b(v) {}, get c() { return 2; }, set c(v) {} })";
+        assert_eq!(
+            inspect(source, InspectOptions::default()),
+            "{ a: [Getter], b: [Setter], c: [Getter/Setter] }"
+        );
+        assert_eq!(
+            inspect(
+                source,
+                InspectOptions {
+                    getters: true,
+                    ..Default::default()
+                }
+            ),
+            "{ a: [Getter: 1], b: [Setter], c: [Getter/Setter: 2] }"
+        );
+    }
+
+    #[test]
+    fn builtins() {
+        let options = InspectOptions::default();
+        assert_eq!(
+            inspect("new Map([['a', 1], [{}, []]])", options),
+            "Map(2) { 'a' => 1, {} => [] }"
+        );
+        assert_eq!(inspect("new Set([1, 2])", options), "Set(2) { 1, 2 }");
+        assert_eq!(
+            inspect("new Uint8Array([1, 2, 3])", options),
+            "Uint8Array(3) [ 1, 2, 3 ]"
+        );
+        assert_eq!(
+            inspect("new Uint8Array([1, 255]).buffer", options),
+            "ArrayBuffer { [Uint8Contents]: <01 ff>, byteLength: 2 }"
+        );
+        assert_eq!(inspect("new Date(0)", options), "1970-01-01T00:00:00.000Z");
+        assert_eq!(inspect("/a+/g", options), "/a+/g");
+        assert_eq!(inspect("new Number(1)", options), "[Number: 1]");
+        assert_eq!(
+            inspect(
+                "[1, 2, 3]",
+                InspectOptions {
+                    max_array_length: 2,
+                    ..options
+                }
+            ),
+            "[ 1, 2, ... 1 more item ]"
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        let options = InspectOptions {
+            break_length: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            inspect(
+                "({ alpha: 1, beta: { gamma: 'delta', epsilon: 2 } })",
+                options
+            ),
+            "{\n  alpha: 1,\n  beta: {\n    gamma: 'delta',\n    epsilon: 2\n  }\n}"
+        );
+    }
+}
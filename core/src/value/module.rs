@@ -1,4 +1,7 @@
-use crate::{qjs, Atom, Context, Ctx, Error, FromAtom, FromJs, IntoJs, Result, Value};
+use super::bytecode;
+use crate::{
+    qjs, Atom, Context, Ctx, Error, FromAtom, FromJs, IntoJs, Object, Result, StdString, Value,
+};
 use std::{
     ffi::{CStr, CString},
     marker::PhantomData,
@@ -76,6 +79,9 @@ module_def_impls! {
     A B C D E F G H I J K L M N O P,
 }
 
+/// The `import.meta` key which holds exports of synthetic module until it evaluated
+const SYNTHETIC_EXPORTS: &str = "__exports";
+
 /// Javascript module with certain exports and imports
 #[derive(Debug, PartialEq)]
 pub struct Module<'js, S = Evaluated>(pub(crate) Value<'js>, pub(crate) PhantomData<S>);
@@ -108,9 +114,7 @@ impl<'js, S> Module<'js, S> {
     pub(crate) fn into_module_def(self) -> *mut qjs::JSModuleDef {
         unsafe { self.0.into_ptr() as _ }
     }
-}
 
-impl<'js> Module<'js> {
     /// Returns the name of the module
     pub fn name<N>(&self) -> Result<N>
     where
@@ -126,19 +130,43 @@ impl<'js> Module<'js> {
         N::from_atom(name)
     }
 
+    /// Returns the modules which was requested by the module
+    ///
+    /// Each item is a tuple of requested _specifier_ and resolved module _name_.
+    /// The requested modules is resolved when module is created and the requests is tracked by the module loader which is set to runtime.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(feature = "doc-cfg", doc(cfg(feature = "loader")))]
+    pub fn requested_modules(&self) -> Result<Vec<(StdString, StdString)>> {
+        let name: StdString = self.name()?;
+        let modules = unsafe { self.0.ctx.get_modules() };
+        Ok(modules
+            .get(&name)
+            .map(|info| {
+                info.requested_modules()
+                    .map(|(specifier, name)| (specifier.into(), name.into()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    pub(crate) fn get_meta(&self) -> Result<Object<'js>> {
+        let ctx = self.0.ctx;
+        Ok(unsafe {
+            Object::from_js_value(
+                ctx,
+                ctx.handle_exception(qjs::JS_GetImportMeta(ctx.as_ptr(), self.as_module_def()))?,
+            )
+        })
+    }
+}
+
+impl<'js> Module<'js> {
     /// Return the `import.meta` object of a module
     pub fn meta<T>(&self) -> Result<T>
     where
         T: FromJs<'js>,
     {
-        let ctx = self.0.ctx;
-        let meta = unsafe {
-            Value::from_js_value(
-                ctx,
-                ctx.handle_exception(qjs::JS_GetImportMeta(ctx.as_ptr(), self.as_module_def()))?,
-            )
-        };
-        T::from_js(ctx, meta)
+        T::from_js(self.0.ctx, self.get_meta()?.0)
     }
 }
 
@@ -222,6 +250,55 @@ impl<'js> Module<'js> {
         Ok(Module(module.0, PhantomData))
     }
 
+    /// Create synthetic JS module which exports the properties of an object
+    ///
+    /// Each own enumerable string property of `exports` becomes a named export of the module.
+    /// The set of export names is fixed at creation time.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new_object<N>(
+        ctx: Ctx<'js>,
+        name: N,
+        exports: Object<'js>,
+    ) -> Result<Module<'js, Loaded<Native>>>
+    where
+        N: Into<Vec<u8>>,
+    {
+        let name = CString::new(name)?;
+        let ptr = unsafe {
+            qjs::JS_NewCModule(
+                ctx.as_ptr(),
+                name.as_ptr(),
+                Some(Module::<Loaded<Native>>::eval_object_fn),
+            )
+        };
+        if ptr.is_null() {
+            return Err(Error::Allocation);
+        }
+        let module = unsafe { Module::<Created>::from_module_def_const(ctx, ptr) };
+        for name in exports.keys::<StdString>() {
+            module.add(name?)?;
+        }
+        // keep exports until module evaluation
+        module.get_meta()?.set(SYNTHETIC_EXPORTS, exports)?;
+        Ok(Module(module.0, PhantomData))
+    }
+
+    /// Create synthetic JS module with the single `default` export
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new_default<N, T>(
+        ctx: Ctx<'js>,
+        name: N,
+        value: T,
+    ) -> Result<Module<'js, Loaded<Native>>>
+    where
+        N: Into<Vec<u8>>,
+        T: IntoJs<'js>,
+    {
+        let exports = Object::new(ctx)?;
+        exports.set("default", value)?;
+        Self::new_object(ctx, name, exports)
+    }
+
     /// Create native JS module by calling init function (like `js_module_init`)
     ///
     /// # Safety
@@ -279,6 +356,10 @@ impl<'js> Module<'js> {
 
 impl<'js> Module<'js, Loaded<Script>> {
     /// Load module from bytecode
+    ///
+    /// The bytecode should be produced by [`Module::write_object`].
+    /// The versioned header of bytecode is validated before loading so an error will be returned
+    /// when bytecode is corrupted or was compiled by incompatible version of QuickJS, for different byte order or pointer width.
     pub fn read_object<B: AsRef<[u8]>>(ctx: Ctx<'js>, buf: B) -> Result<Self> {
         Self::read_object_raw(ctx, buf.as_ref(), qjs::JS_READ_OBJ_BYTECODE as _)
     }
@@ -293,6 +374,7 @@ impl<'js> Module<'js, Loaded<Script>> {
     }
 
     fn read_object_raw(ctx: Ctx<'js>, buf: &[u8], flags: qjs::c_int) -> Result<Self> {
+        let buf = bytecode::strip_header(buf)?;
         let value = unsafe {
             Value::from_js_value(
                 ctx,
@@ -308,6 +390,9 @@ impl<'js> Module<'js, Loaded<Script>> {
     }
 
     /// Write bytecode of loaded module
+    ///
+    /// The bytecode is prepended by the versioned header which is validated when reading.
+    /// The `byte_swap` can be used to produce bytecode for targets with different byte order.
     pub fn write_object(&self, byte_swap: bool) -> Result<Vec<u8>> {
         let ctx = self.0.ctx;
         let mut len = MaybeUninit::uninit();
@@ -328,7 +413,7 @@ impl<'js> Module<'js, Loaded<Script>> {
         }
         let len = unsafe { len.assume_init() };
         let obj = unsafe { from_raw_parts(buf, len as _) };
-        let obj = Vec::from(obj);
+        let obj = bytecode::add_header(obj, byte_swap);
         unsafe { qjs::js_free(ctx.as_ptr(), buf as _) };
         Ok(obj)
     }
@@ -357,6 +442,32 @@ impl<'js> Module<'js, Loaded<Native>> {
         Ok(())
     }
 
+    unsafe extern "C" fn eval_object_fn(
+        ctx: *mut qjs::JSContext,
+        ptr: *mut qjs::JSModuleDef,
+    ) -> qjs::c_int {
+        let ctx = Ctx::from_ptr(ctx);
+        let module = Self::from_module_def_const(ctx, ptr);
+        match module.set_object_exports() {
+            Ok(_) => 0,
+            Err(error) => {
+                error.throw(ctx);
+                -1
+            }
+        }
+    }
+
+    fn set_object_exports(&self) -> Result<()> {
+        let meta = self.get_meta()?;
+        let exports: Object = meta.get(SYNTHETIC_EXPORTS)?;
+        meta.remove(SYNTHETIC_EXPORTS)?;
+        for result in exports.props::<StdString, Value>() {
+            let (name, value) = result?;
+            self.set(name, value)?;
+        }
+        Ok(())
+    }
+
     unsafe extern "C" fn eval_fn<D>(
         ctx: *mut qjs::JSContext,
         ptr: *mut qjs::JSModuleDef,
@@ -386,7 +497,13 @@ impl<'js, S> Module<'js, Loaded<S>> {
             let ret = qjs::JS_EvalFunction(ctx.as_ptr(), qjs::JS_DupValue(self.0.value));
             ctx.handle_exception(ret)?;
         }
-        Ok(Module(self.0, PhantomData))
+        let module = Module(self.0, PhantomData);
+        #[cfg(feature = "loader")]
+        {
+            let name: StdString = module.name()?;
+            unsafe { ctx.get_modules() }.set_evaluated(&name);
+        }
+        Ok(module)
     }
 
     /// Cast the specific loaded module to generic one
@@ -569,6 +686,29 @@ mod test {
         })
     }
 
+    #[test]
+    fn from_object() {
+        test_with(|ctx| {
+            let exports = Object::new(ctx).unwrap();
+            exports.set("n", 123).unwrap();
+            exports.set("s", "abc").unwrap();
+            let module = Module::new_object(ctx, "object_mod", exports)
+                .unwrap()
+                .eval()
+                .unwrap();
+
+            #[cfg(feature = "exports")]
+            {
+                let n: i32 = module.get("n").unwrap();
+                assert_eq!(n, 123);
+                let s: StdString = module.get("s").unwrap();
+                assert_eq!(s, "abc");
+            }
+            #[cfg(not(feature = "exports"))]
+            let _ = module;
+        })
+    }
+
     #[test]
     fn from_javascript() {
         test_with(|ctx| {
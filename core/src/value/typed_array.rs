@@ -1,12 +1,5 @@
 use crate::{qjs, ArrayBuffer, Ctx, Error, FromJs, Function, IntoJs, Object, Result, Value};
-use std::{
-    convert::TryFrom,
-    marker::PhantomData,
-    mem::{size_of, MaybeUninit},
-    ops::Deref,
-    ptr::null_mut,
-    slice,
-};
+use std::{convert::TryFrom, marker::PhantomData, mem::size_of, ops::Deref, ptr::null_mut, slice};
 
 /// The trait which implements types which capable to be TypedArray items
 ///
@@ -161,35 +154,12 @@ impl<'js, T> TypedArray<'js, T> {
     }
 
     pub(crate) fn get_raw(val: &Value<'js>) -> Option<(usize, *mut T)> {
-        let ctx = val.ctx;
-        let val = val.as_js_value();
-        let mut off = MaybeUninit::<qjs::size_t>::uninit();
-        let mut len = MaybeUninit::<qjs::size_t>::uninit();
-        let mut stp = MaybeUninit::<qjs::size_t>::uninit();
-        let buf = unsafe {
-            let val = qjs::JS_GetTypedArrayBuffer(
-                ctx.as_ptr(),
-                val,
-                off.as_mut_ptr(),
-                len.as_mut_ptr(),
-                stp.as_mut_ptr(),
-            );
-            ctx.handle_exception(val).ok()?;
-            Value::from_js_value(ctx, val)
-        };
-        let off = unsafe { off.assume_init() } as usize;
-        let len = unsafe { len.assume_init() } as usize;
-        let stp = unsafe { stp.assume_init() } as usize;
+        let (len, stp, ptr) = ArrayBuffer::get_typed_raw(val)?;
         if stp != size_of::<T>() {
             return None;
         }
-        let (full_len, ptr) = ArrayBuffer::get_raw(&buf)?;
-        if (off + len) > full_len {
-            return None;
-        }
         let len = len / size_of::<T>();
-        let ptr = unsafe { ptr.add(off) } as *mut T;
-        Some((len, ptr))
+        Some((len, ptr as *mut T))
     }
 }
 
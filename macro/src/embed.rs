@@ -24,8 +24,7 @@ pub use attrs::*;
 use crate::{Config, PubVis, TokenStream};
 use ident_case::RenameRule;
 use quote::{format_ident, quote};
-use rquickjs_core::{Compile, Context, FileResolver, Module, Result, Runtime, ScriptLoader};
-use std::path::Path;
+use rquickjs_core::bundle::Builder;
 use syn::ItemMod;
 
 #[cfg(feature = "phf")]
@@ -83,53 +82,28 @@ impl Embedder {
         }
         let public = public.as_ref().map(PubVis::override_tokens);
 
-        let compile = Compile::new();
-
-        let mut resolver = compile.resolver(FileResolver::default());
+        let mut builder = Builder::new();
         for path in &paths {
-            resolver.add_path(path);
+            builder.add_path(path);
         }
         for pattern in &patterns {
-            resolver.add_pattern(pattern);
+            builder.add_pattern(pattern);
         }
-
-        let mut loader = compile.loader(ScriptLoader::default());
-
-        for pattern in &patterns {
-            if let Some(extension) = Path::new(pattern)
-                .extension()
-                .and_then(|extension| extension.to_str())
-            {
-                loader.add_extension(extension);
-            }
+        for name in &names {
+            builder.add_module(name);
         }
 
-        if let Err(error) = (|| -> Result<()> {
-            let rt = Runtime::new()?;
-            let ctx = Context::full(&rt)?;
-
-            rt.set_loader(resolver, loader);
-
-            let source = names
-                .iter()
-                .map(|name| format!("import '{name}';"))
-                .collect::<Vec<_>>()
-                .join("");
-
-            ctx.with(|ctx| {
-                let _ = Module::new(ctx, "<main>", source)?;
-                Ok(())
-            })
-        })() {
-            error!(ident, "Error when embedding JS modules: {}", error);
-            return quote!();
-        }
+        let bytecodes = match builder.compile() {
+            Ok(bytecodes) => bytecodes,
+            Err(error) => {
+                error!(ident, "Error when embedding JS modules: {}", error);
+                return quote!();
+            }
+        };
 
-        let entries = compile
-            .bytecodes()
+        let entries = bytecodes
             .into_iter()
             .map(|(name, data)| {
-                let name = name.to_string();
                 let data = if test {
                     quote! { &[0u8, 1u8, 2u8, 3u8] }
                 } else {
@@ -0,0 +1,128 @@
+/// The lexical state of scanner
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    String(char),
+    Template,
+    LineComment,
+    BlockComment,
+}
+
+/// Check that the source is not complete yet and more lines should be read
+///
+/// The source is incomplete when it has unclosed brackets, template literals, block comments
+/// or strings which is continued to the next line using backslash.
+/// The regular expression literals is not recognized, so unbalanced brackets inside it may confuse detection.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut state = State::Code;
+    // The stack of opened brackets (`{` for both blocks and template substitutions)
+    let mut brackets = Vec::new();
+    // The stack of template substitution depths
+    let mut templates = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match state {
+            State::Code => match c {
+                '\'' | '"' => state = State::String(c),
+                '`' => state = State::Template,
+                '/' if chars.peek() == Some(&'/') => state = State::LineComment,
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    state = State::BlockComment;
+                }
+                '(' | '[' | '{' => brackets.push(c),
+                ')' | ']' => {
+                    brackets.pop();
+                }
+                '}' => {
+                    if templates.last() == Some(&brackets.len()) {
+                        templates.pop();
+                        state = State::Template;
+                    } else {
+                        brackets.pop();
+                    }
+                }
+                _ => {}
+            },
+            State::String(quote) => match c {
+                // The escaped character is skipped
+                '\\' if chars.next().is_none() => return true,
+                '\\' => {}
+                '\n' => state = State::Code,
+                _ if c == quote => state = State::Code,
+                _ => {}
+            },
+            State::Template => match c {
+                '\\' => {
+                    chars.next();
+                }
+                '`' => state = State::Code,
+                '$' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    templates.push(brackets.len());
+                    state = State::Code;
+                }
+                _ => {}
+            },
+            State::LineComment => {
+                if c == '\n' {
+                    state = State::Code;
+                }
+            }
+            State::BlockComment => {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    state = State::Code;
+                }
+            }
+        }
+    }
+
+    matches!(state, State::Template | State::BlockComment)
+        || !brackets.is_empty()
+        || !templates.is_empty()
+        || source.trim_end().ends_with('\\')
+}
+
+/// Check that the source should be evaluated as a module
+pub fn is_module(source: &str) -> bool {
+    let source = source.trim_start();
+    ["import", "export"].iter().any(|keyword| {
+        source.starts_with(keyword)
+            && source[keyword.len()..]
+                .chars()
+                .next()
+                .map(|c| c.is_whitespace() || c == '{' || c == '*' || c == '"' || c == '\'')
+                .unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn incomplete() {
+        assert!(!is_incomplete("1 + 2"));
+        assert!(!is_incomplete("function f() { return 1; }"));
+        assert!(is_incomplete("function f() {\n"));
+        assert!(is_incomplete("[1,\n2,"));
+        assert!(is_incomplete("`a\n"));
+        assert!(is_incomplete("`${ {a: 1"));
+        assert!(!is_incomplete("`${ {a: 1}.a }`"));
+        assert!(is_incomplete("/* comment"));
+        assert!(!is_incomplete("'{' // (\n"));
+        assert!(is_incomplete("'abc\\\n"));
+    }
+
+    #[test]
+    fn module() {
+        assert!(is_module("import { a } from 'a';"));
+        assert!(is_module("import 'a';"));
+        assert!(is_module("export const a = 1;"));
+        assert!(!is_module("import('a')"));
+        assert!(!is_module("import.meta"));
+        assert!(!is_module("exports = 1"));
+    }
+}
@@ -0,0 +1,275 @@
+//! # Interactive JS shell
+//!
+//! The REPL evaluates the entered code in the full context and prints results.
+//! The input which has unclosed brackets, template literals or comments is continued on the next line.
+//! The statements which starts with `import` or `export` keywords is evaluated as modules.
+
+use rquickjs::{
+    console::Console, Context, EvalOptions, FileResolver, InspectOptions, Module, NativeLoader,
+    Result, Runtime, ScriptLoader, Value,
+};
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    process,
+};
+
+mod input;
+
+const USAGE: &str = "\
+Usage: rquickjs-repl [OPTIONS] [FILE]...
+
+Start interactive JS shell, the files is loaded before the session
+
+Options:
+  -I, --path <DIR>    Add search path for modules (default is current directory)
+  -n, --native        Enable loading of native modules
+  -h, --help          Print this help
+  -V, --version       Print version
+";
+
+const HELP: &str = "\
+.break          Discard the current multi-line input
+.exit           Exit the REPL
+.help           Print this help
+.load <FILE>    Load JS file into the session
+
+The statements which starts with 'import' or 'export' is evaluated as modules.
+Use dynamic 'import()' to get the module namespace as value.
+";
+
+/// Command line options
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    paths: Vec<String>,
+    native: bool,
+    files: Vec<String>,
+}
+
+/// The action which is requested by command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run(Options),
+    Help,
+    Version,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> std::result::Result<Command, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-I" | "--path" => options.paths.push(
+                args.next()
+                    .ok_or_else(|| format!("Missing value of option '{}'", arg))?,
+            ),
+            "-n" | "--native" => options.native = true,
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option '{}'", arg))
+            }
+            _ => options.files.push(arg),
+        }
+    }
+
+    Ok(Command::Run(options))
+}
+
+/// The REPL session
+struct Repl {
+    rt: Runtime,
+    ctx: Context,
+    // The counter of evaluated modules to give them unique names
+    modules: usize,
+}
+
+impl Repl {
+    fn new(options: &Options) -> Result<Self> {
+        let rt = Runtime::new()?;
+        let ctx = Context::full(&rt)?;
+        ctx.with(|ctx| Console::default().init(ctx))?;
+
+        let mut resolver = FileResolver::default();
+        if options.paths.is_empty() {
+            resolver.add_path(".");
+        }
+        for path in &options.paths {
+            resolver.add_path(path.as_str());
+        }
+        if options.native {
+            resolver.add_native();
+            rt.set_loader(resolver, (ScriptLoader::default(), NativeLoader::default()));
+        } else {
+            rt.set_loader(resolver, ScriptLoader::default());
+        }
+
+        Ok(Self {
+            rt,
+            ctx,
+            modules: 0,
+        })
+    }
+
+    /// Evaluate the source and print the result
+    fn eval(&mut self, source: &str) {
+        let result = if input::is_module(source) {
+            self.modules += 1;
+            let name = format!("<repl:{}>", self.modules);
+            self.ctx.with(|ctx| {
+                Module::new(ctx, name, source)
+                    .and_then(|module| module.eval())
+                    .map(|_| None)
+            })
+        } else {
+            self.ctx.with(|ctx| {
+                let value = ctx.eval_with_options::<Value, _>(source, script_options())?;
+                print_value(ctx, value).map(Some)
+            })
+        };
+        self.report(result);
+    }
+
+    /// Load the file into the session
+    fn load(&mut self, path: &str) {
+        let result = self.ctx.with(|ctx| {
+            ctx.eval_file_with_options::<Value, _>(path, script_options())
+                .map(|_| None)
+        });
+        self.report(result);
+    }
+
+    fn report(&mut self, result: Result<Option<String>>) {
+        match result {
+            Ok(Some(output)) => println!("{}", output),
+            Ok(None) => {}
+            Err(error) => eprintln!("Uncaught {}", error),
+        }
+        self.run_jobs();
+    }
+
+    fn run_jobs(&self) {
+        while self.rt.is_job_pending() {
+            if let Err(error) = self.rt.execute_pending_job() {
+                eprintln!("Uncaught {}", error);
+            }
+        }
+    }
+}
+
+fn script_options() -> EvalOptions {
+    EvalOptions {
+        global: true,
+        strict: false,
+        backtrace_barrier: false,
+    }
+}
+
+/// Format the value and store it as `_` like Node does
+fn print_value<'js>(ctx: rquickjs::Ctx<'js>, value: Value<'js>) -> Result<String> {
+    let output = value.inspect(InspectOptions::default())?;
+    if value.type_of() != rquickjs::Type::Undefined {
+        ctx.globals().set("_", value)?;
+    }
+    Ok(output)
+}
+
+fn run(options: &Options) -> Result<()> {
+    let mut repl = Repl::new(options)?;
+    for file in &options.files {
+        repl.load(file);
+    }
+
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let mut stdout = io::stdout();
+    let mut buffer = String::new();
+
+    println!(
+        "Welcome to rquickjs-repl {}. Type \".help\" for more information.",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    loop {
+        write!(stdout, "{}", if buffer.is_empty() { "> " } else { "... " })?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            break;
+        }
+
+        let command = line.trim();
+        if command == ".break" {
+            buffer.clear();
+            continue;
+        }
+        if buffer.is_empty() && command.starts_with('.') {
+            let (command, arg) = match command.find(char::is_whitespace) {
+                Some(pos) => (&command[..pos], command[pos..].trim()),
+                None => (command, ""),
+            };
+            match command {
+                ".exit" => break,
+                ".help" => print!("{}", HELP),
+                ".load" if !arg.is_empty() => repl.load(arg),
+                ".load" => eprintln!("Missing file name"),
+                _ => eprintln!("Invalid REPL keyword '{}'", command),
+            }
+            continue;
+        }
+
+        buffer.push_str(&line);
+        if input::is_incomplete(&buffer) {
+            continue;
+        }
+        if !buffer.trim().is_empty() {
+            repl.eval(&buffer);
+        }
+        buffer.clear();
+    }
+
+    Ok(())
+}
+
+fn main() {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Run(options)) => {
+            if let Err(error) = run(&options) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Help) => print!("{}", USAGE),
+        Ok(Command::Version) => println!("rquickjs-repl {}", env!("CARGO_PKG_VERSION")),
+        Err(error) => {
+            eprintln!("Error: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(
+            parse(&["-n", "-I", "lib", "init.js"]),
+            Ok(Command::Run(Options {
+                paths: vec!["lib".into()],
+                native: true,
+                files: vec!["init.js".into()],
+            }))
+        );
+        assert_eq!(parse(&["--version"]), Ok(Command::Version));
+        assert!(parse(&["-I"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+    }
+}
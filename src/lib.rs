@@ -41,6 +41,17 @@
 //! - `rust-alloc` forces using Rust's global allocator by default instead of libc's one.
 //! - `loader` adds support for custom ES6 modules resolvers and loaders. The resolvers and loaders should implements [`Resolver`] and [`Loader`] traits respectively and can be plugged in already existing [`Runtime`] before loading modules via [`Runtime::set_loader`]. The resolvers and loaders can be easily combined via tuples. When the previous resolver or loader failed the next one will be applied.
 //! - `dyn-load` adds support for loadable native modules (so/dll/dylib).
+//! - `archive-tar` and `archive-zip` adds support for loading modules from tar and zip archives using [`ArchiveFs`].
+//! - `commonjs` adds support for CommonJS modules. The [`CommonJs`] installs `require` function and [`CommonJsLoader`] allows importing CommonJS modules from ES6 modules.
+//! - `bundle` adds the [`bundle::Builder`] which compiles modules into bytecode at build time and generates the [`Bundle`] data like the `embed` macro does.
+//! - `console` adds the [`console::Console`] built-in which routes messages to the user-defined [`console::Sink`]. The features `log` and `tracing` adds sinks for the same named crates.
+//! - `encoding` adds the [`encoding::Encoding`] built-in which installs `TextEncoder`, `TextDecoder`, `atob` and `btoa` globals.
+//! - `url` adds the [`url::UrlApi`] built-in which installs `URL` and `URLSearchParams` classes backed by the [`url`](https://crates.io/crates/url) crate.
+//! - `crypto` adds the [`crypto::Crypto`] built-in which installs `crypto` global with `getRandomValues`, `randomUUID` and `subtle.digest` for SHA-1 and SHA-2 algorithms.
+//! - `fs` adds the [`fs::Fs`] native module which gives the scripts access to the files under configured root directories with per-root read-only or read-write permissions. The module can be loaded using [`SyntheticLoader::with_module_fn`].
+//! - `pool` adds the [`pool::RuntimePool`] which keeps the runtimes with pre-initialized contexts for reuse.
+//! - `timers` adds the [`timers::Timers`] built-in which installs `setTimeout`, `setInterval` and `queueMicrotask` globals backed by the async executor. The sleeping is provided by the [`timers::Timer`] which is implemented for async runtime markers and [`timers::ManualClock`].
+//! - `fetch` adds the [`fetch::Fetch`] built-in which installs `fetch` function with `Request`, `Response` and `Headers` classes. The requests is served by the user-defined [`fetch::FetchHandler`] and the bodies can be streamed.
 //! - `array-buffer` adds support for [`ArrayBuffer`] and [`TypedArray`].
 //! - `futures` adds support for async Rust. When enabled the Rust futures can be passed to JS as [ES6 Promises](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Promise) and ES6 Promises can be given back as Rust futures.
 //! - `tokio` adds integration with [`tokio`] async runtime. The method [`Runtime::spawn_executor`] can be used with [`Tokio`] to spawn async executor.
@@ -5,6 +5,9 @@ use std::{
     process::{Command, Stdio},
 };
 
+/// The version of bundled QuickJS library
+const QUICKJS_VERSION: &str = "2020-01-19";
+
 fn main() {
     #[cfg(feature = "logging")]
     pretty_env_logger::init();
@@ -67,9 +70,12 @@ fn main() {
         "atomic_new_class_id.patch",
     ];
 
+    println!("cargo:rustc-env=QUICKJS_VERSION={}", QUICKJS_VERSION);
+    let version_define = format!("\"{}\"", QUICKJS_VERSION);
+
     let mut defines = vec![
         ("_GNU_SOURCE".into(), None),
-        ("CONFIG_VERSION".into(), Some("\"2020-01-19\"")),
+        ("CONFIG_VERSION".into(), Some(version_define.as_str())),
         ("CONFIG_BIGNUM".into(), None),
     ];
 
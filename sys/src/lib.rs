@@ -8,6 +8,9 @@
 
 use std::ptr;
 
+/// The version of QuickJS library
+pub const VERSION: &str = env!("QUICKJS_VERSION");
+
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 #[cfg(not(feature = "bindgen"))]